@@ -0,0 +1,193 @@
+//! Build script helpers for the macros in [`file_env_const`](https://docs.rs/file_env_const).
+//!
+//! Procedural macros can't emit `cargo:rerun-if-*` directives, so cargo doesn't know that a crate
+//! using the macros depends on the files and environment variables they read, and an incremental
+//! build can keep embedding a value after its source has changed. Calling these from the crate's
+//! `build.rs`, with this crate as a build dependency, tells cargo to rebuild the crate when any of
+//! them change.
+//!
+//! ```no_run
+//! // In main() in build.rs
+//! file_env_const_build::track(["config.toml"], ["APP_KEY"]);
+//! ```
+//!
+//! To keep the list in sync with the macros, set `FILE_ENV_CONST_MANIFEST` for every build, such
+//! as in the `[env]` table of `.cargo/config.toml`, and track the sources recorded in the audit
+//! manifest by the previous build instead
+//! ```no_run
+//! // In main() in build.rs
+//! file_env_const_build::track_manifest("target/file_env_const.jsonl").unwrap();
+//! ```
+//!
+//! Build time settings such as `FILE_ENV_CONST_STRICT` and `FILE_ENV_CONST_OFFLINE` are always
+//! tracked, as is the `FILE_ENV_CONST_OVERRIDE_<NAME>` override of each environment variable.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+
+/// The build time environment variables which change how every value is resolved
+const SETTINGS: &[&str] = &[
+    "FILE_ENV_CONST_LOG",
+    "FILE_ENV_CONST_LOG_FILE",
+    "FILE_ENV_CONST_OFFLINE",
+    "FILE_ENV_CONST_PROVIDER",
+    "FILE_ENV_CONST_REPRODUCIBLE",
+    "FILE_ENV_CONST_STRICT",
+    "SOURCE_DATE_EPOCH",
+];
+
+/// Tells cargo to rebuild the crate when any of `files` or `env_vars` change
+///
+/// Relative paths are resolved from the crate root, like the macros' filenames. Environment
+/// variables are named as the macros look them up, after any `env_prefix`.
+///
+/// # Examples
+/// ```
+/// file_env_const_build::track(["config.toml", "keys/prod.pem"], ["APP_KEY"]);
+/// file_env_const_build::track::<&str, _>([], ["CARGO_PKG_NAME"]);
+/// ```
+pub fn track<P, E>(files: impl IntoIterator<Item = P>, env_vars: impl IntoIterator<Item = E>)
+where
+    P: AsRef<Path>,
+    E: AsRef<str>,
+{
+    for file in files {
+        println!("cargo:rerun-if-changed={}", file.as_ref().display());
+    }
+    let env_vars: BTreeSet<String> = env_vars
+        .into_iter()
+        .flat_map(|name| [name.as_ref().to_string(), override_name(name.as_ref())])
+        .chain(SETTINGS.iter().map(|name| name.to_string()))
+        .collect();
+    for name in env_vars {
+        println!("cargo:rerun-if-env-changed={}", name);
+    }
+}
+
+/// Tells cargo to rebuild the crate when any file or environment variable recorded in the audit
+/// manifest at `path` changes, including those which were looked up but not found
+///
+/// The manifest is written while the crate is compiled, after the build script runs, so this
+/// tracks the sources used by the previous build. If the manifest doesn't exist yet, the build
+/// script is run again once it does.
+///
+/// # Errors
+/// Returns an error if the manifest exists but can't be read.
+///
+/// # Examples
+/// ```
+/// let dir = std::env::temp_dir().join("file_env_const_build_doctest");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let manifest = dir.join("manifest.jsonl");
+/// let record = concat!(
+///     r#"{"macro":"file_env","call_site":"src/main.rs:1:17","source":"env","#,
+///     r#""name":"APP_KEY","sha256":"00","hermetic":false,"#,
+///     r#""looked_up":["file:/app/config.toml","env:APP_KEY"]}"#,
+/// );
+/// std::fs::write(&manifest, record).unwrap();
+/// file_env_const_build::track_manifest(&manifest).unwrap();
+/// ```
+pub fn track_manifest(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            // Runs the build script again once the first build has written the manifest
+            println!("cargo:rerun-if-changed={}", path.display());
+            String::new()
+        }
+        Err(e) => return Err(e),
+    };
+    let mut files = BTreeSet::new();
+    let mut env_vars = BTreeSet::new();
+    for line in contents.lines() {
+        let used = field(line, "source").zip(field(line, "name"));
+        for (source, name) in used.into_iter().chain(looked_up(line)) {
+            // The prefix of env_prefix_map!, which can't be tracked as a single variable
+            if name.ends_with('*') {
+                continue;
+            }
+            match source.as_str() {
+                "file" => {
+                    files.insert(name);
+                }
+                "env" => {
+                    env_vars.insert(name);
+                }
+                _ => {}
+            }
+        }
+    }
+    track(files, env_vars);
+    Ok(())
+}
+
+/// Returns the name of the `FILE_ENV_CONST_OVERRIDE_<NAME>` override for the environment variable
+/// `name`
+fn override_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("FILE_ENV_CONST_OVERRIDE_{}", name)
+}
+
+/// Returns the string member `key` of the manifest record `line`, unescaped
+///
+/// Records are written as a single line of JSON with no whitespace between tokens, so this
+/// doesn't need a full JSON parser.
+fn field(line: &str, key: &str) -> Option<String> {
+    let start = line.find(&format!("\"{}\":\"", key))? + key.len() + 4;
+    string(&line[start..]).map(|(s, _)| s)
+}
+
+/// Returns the kind and name of each source in the `looked_up` member of the manifest record
+/// `line`, which are written as `<kind>:<name>`
+fn looked_up(line: &str) -> Vec<(String, String)> {
+    let mut sources = Vec::new();
+    let Some(start) = line.find("\"looked_up\":[") else {
+        return sources;
+    };
+    let mut rest = &line[start + 13..];
+    while let Some(after_quote) = rest.strip_prefix('"') {
+        let Some((source, len)) = string(after_quote) else {
+            break;
+        };
+        if let Some((kind, name)) = source.split_once(':') {
+            sources.push((kind.to_string(), name.to_string()));
+        }
+        rest = after_quote[len..].trim_start_matches(',');
+    }
+    sources
+}
+
+/// Unescapes the JSON string starting at `s`, just after its opening quote, returning it and the
+/// length of `s` it took up including the closing quote
+fn string(s: &str) -> Option<(String, usize)> {
+    let mut out = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, i + 1)),
+            '\\' => match chars.next()?.1 {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
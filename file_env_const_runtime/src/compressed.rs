@@ -0,0 +1,114 @@
+use std::fmt;
+use std::io::Read;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+/// A string embedded with the `compress` option, which is decompressed on first use
+///
+/// ```
+/// use file_env_const_runtime::Compressed;
+///# #[cfg(feature = "gzip")]
+///# {
+///
+/// let compressed = file_env_const_runtime::gzip_compress(b"hello hello hello hello");
+/// let compressed: &'static [u8] = compressed.leak();
+///
+/// let data = Compressed::gzip(compressed);
+/// assert_eq!(&*data, "hello hello hello hello");
+///# }
+/// ```
+pub struct Compressed {
+    data: &'static [u8],
+    decompress: fn(&[u8]) -> Vec<u8>,
+    value: OnceLock<String>,
+}
+
+impl Compressed {
+    /// Wraps `data` which was compressed with gzip
+    #[cfg(feature = "gzip")]
+    #[doc(hidden)]
+    pub const fn gzip(data: &'static [u8]) -> Self {
+        Compressed {
+            data,
+            decompress: gzip_decompress,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// Wraps `data` which was compressed with zstd
+    #[cfg(feature = "zstd")]
+    #[doc(hidden)]
+    pub const fn zstd(data: &'static [u8]) -> Self {
+        Compressed {
+            data,
+            decompress: zstd_decompress,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// Returns the original value, decompressing it if this is the first use
+    pub fn get(&self) -> &str {
+        self.value.get_or_init(|| {
+            String::from_utf8((self.decompress)(self.data))
+                .expect("compressed data was valid UTF-8 when embedded")
+        })
+    }
+
+    /// Returns the size of the compressed data which is embedded
+    pub fn compressed_len(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl Deref for Compressed {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.get()
+    }
+}
+
+impl fmt::Debug for Compressed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Compressed").field(&self.get()).finish()
+    }
+}
+
+/// Compresses `data` with gzip, for use by the macros
+#[cfg(feature = "gzip")]
+#[doc(hidden)]
+pub fn gzip_compress(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .expect("compressing in memory can't fail")
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_decompress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    flate2::read::GzDecoder::new(data)
+        .read_to_end(&mut out)
+        .expect("embedded gzip data is valid");
+    out
+}
+
+/// Compresses `data` with zstd, for use by the macros
+#[cfg(feature = "zstd")]
+#[doc(hidden)]
+pub fn zstd_compress(data: &[u8]) -> Vec<u8> {
+    ruzstd::encoding::compress_to_vec(data, ruzstd::encoding::CompressionLevel::Fastest)
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    ruzstd::decoding::StreamingDecoder::new(data)
+        .expect("embedded zstd data is valid")
+        .read_to_end(&mut out)
+        .expect("embedded zstd data is valid");
+    out
+}
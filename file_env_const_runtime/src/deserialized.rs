@@ -0,0 +1,48 @@
+//! Deserializing values embedded with the `deserialize` option, which the macros expand to a
+//! `LazyLock` calling one of these on first use
+
+/// Deserializes JSON embedded with `deserialize = "json"`
+///
+/// The JSON was checked when it was embedded, so this only panics if it doesn't have the shape of
+/// `T`. Strings can be borrowed from the embedded value.
+///
+/// ```
+///# #[cfg(feature = "json")]
+///# {
+/// use std::collections::HashMap;
+/// use std::sync::LazyLock;
+///
+/// static PORTS: LazyLock<HashMap<&str, u16>> =
+///     LazyLock::new(|| file_env_const_runtime::from_json(r#"{"http":80,"https":443}"#));
+/// assert_eq!(PORTS["https"], 443);
+///# }
+/// ```
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn from_json<T: serde::Deserialize<'static>>(data: &'static str) -> T {
+    serde_json::from_str(data)
+        .unwrap_or_else(|e| panic!("The embedded JSON doesn't match the type: {}", e))
+}
+
+/// Deserializes TOML embedded with `deserialize = "toml"`
+///
+/// The TOML was checked when it was embedded, so this only panics if it doesn't have the shape of
+/// `T`.
+///
+/// ```
+///# #[cfg(feature = "toml")]
+///# {
+/// use std::collections::HashMap;
+/// use std::sync::LazyLock;
+///
+/// static PORTS: LazyLock<HashMap<String, u16>> =
+///     LazyLock::new(|| file_env_const_runtime::from_toml("http = 80\nhttps = 443"));
+/// assert_eq!(PORTS["https"], 443);
+///# }
+/// ```
+#[cfg(feature = "toml")]
+#[doc(hidden)]
+pub fn from_toml<T: serde::de::DeserializeOwned>(data: &str) -> T {
+    toml::from_str(data)
+        .unwrap_or_else(|e| panic!("The embedded TOML doesn't match the type: {}", e))
+}
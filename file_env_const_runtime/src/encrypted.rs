@@ -0,0 +1,104 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+/// A string embedded with the `encrypt` option, which can only be read with the key it was
+/// encrypted with
+///
+/// The value is encrypted with ChaCha20-Poly1305 at build time, and decrypted on first use.
+///
+/// ```
+/// use file_env_const_runtime::Encrypted;
+///
+/// let key = [7; 32];
+/// let nonce = [3; 12];
+/// let ciphertext = file_env_const_runtime::encrypt(b"secret", &key, &nonce);
+/// let ciphertext: &'static [u8] = ciphertext.leak();
+///
+/// let secret = Encrypted::new(ciphertext, nonce);
+/// assert!(secret.decrypt(&[0; 32]).is_err());
+/// assert_eq!(secret.decrypt(&key), Ok("secret"));
+/// ```
+pub struct Encrypted {
+    ciphertext: &'static [u8],
+    nonce: [u8; 12],
+    value: OnceLock<String>,
+}
+
+impl Encrypted {
+    /// Wraps `ciphertext` which was encrypted with `nonce`
+    #[doc(hidden)]
+    pub const fn new(ciphertext: &'static [u8], nonce: [u8; 12]) -> Self {
+        Encrypted {
+            ciphertext,
+            nonce,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// Returns the original value, decrypting it with `key` if this is the first use
+    ///
+    /// Once decrypted the value is kept, and later calls return it whatever key they're given
+    pub fn decrypt(&self, key: &[u8; 32]) -> Result<&str, DecryptError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&self.nonce), self.ciphertext)
+            .map_err(|_| DecryptError)?;
+        let value = String::from_utf8(plaintext).map_err(|_| DecryptError)?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// The same as [`Encrypted::decrypt`], with the key given as 64 hex digits
+    pub fn decrypt_hex(&self, key: &str) -> Result<&str, DecryptError> {
+        self.decrypt(&parse_hex_key(key).ok_or(DecryptError)?)
+    }
+}
+
+impl fmt::Debug for Encrypted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Encrypted(..)")
+    }
+}
+
+/// The key was wrong, or the embedded value has been tampered with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecryptError;
+
+impl fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to decrypt embedded value")
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
+/// Encrypts `plaintext` with `key` and `nonce`
+///
+/// This is used by the macros, which derive the nonce from the key and plaintext so that builds
+/// are reproducible.
+#[doc(hidden)]
+pub fn encrypt(plaintext: &[u8], key: &[u8; 32], nonce: &[u8; 12]) -> Vec<u8> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(nonce), plaintext)
+        .expect("encrypting in memory can't fail")
+}
+
+/// Parses a key given as 64 hex digits
+#[doc(hidden)]
+pub fn parse_hex_key(key: &str) -> Option<[u8; 32]> {
+    let key = key.trim();
+    if key.len() != 64 || !key.is_ascii() {
+        return None;
+    }
+    let mut out = [0; 32];
+    for (byte, digits) in out.iter_mut().zip(key.as_bytes().chunks_exact(2)) {
+        let digits = std::str::from_utf8(digits).ok()?;
+        *byte = u8::from_str_radix(digits, 16).ok()?;
+    }
+    Some(out)
+}
@@ -0,0 +1,86 @@
+//! Runtime support for the macros in [`file_env_const`](https://docs.rs/file_env_const).
+//!
+//! Procedural macro crates can only export macros, so any types which the macros expand to live
+//! here. Add this crate as a dependency alongside `file_env_const` when using a macro which
+//! refers to it, such as `file_env_with_source!`.
+//!
+//! # Features
+//! * `encrypt`: provides `Encrypted`, for values embedded with the `encrypt` option
+//! * `gzip` and `zstd`: provide `Compressed`, for values embedded with the `compress` option
+//! * `json` and `toml`: deserialize values embedded with the `deserialize` option with serde
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compressed;
+#[cfg(any(feature = "json", feature = "toml"))]
+mod deserialized;
+#[cfg(feature = "encrypt")]
+mod encrypted;
+mod obfuscated;
+
+use std::fmt;
+
+#[cfg(feature = "gzip")]
+pub use compressed::gzip_compress;
+#[cfg(feature = "zstd")]
+pub use compressed::zstd_compress;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compressed::Compressed;
+#[cfg(feature = "json")]
+pub use deserialized::from_json;
+#[cfg(feature = "toml")]
+pub use deserialized::from_toml;
+#[cfg(feature = "encrypt")]
+pub use encrypted::{encrypt, parse_hex_key, DecryptError, Encrypted};
+pub use obfuscated::{xor_keystream, Obfuscated};
+
+/// The source a value was resolved from at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Source {
+    /// The value was read from a file
+    File,
+    /// The value was read from an environment variable
+    Env,
+    /// The value was fetched from a URL
+    Http,
+    /// The value was the output of a command
+    Cmd,
+    /// The value was fetched by a secret provider's command
+    Provider,
+    /// The value was read from the git repository
+    Git,
+    /// The value was the time of the build
+    Clock,
+    /// None of the other sources were found, and the fallback string was used
+    Default,
+}
+
+impl Source {
+    /// Returns true if the value is the baked-in fallback rather than an injected one
+    ///
+    /// ```
+    /// use file_env_const_runtime::Source;
+    ///
+    /// assert!(Source::Default.is_default());
+    /// assert!(!Source::Env.is_default());
+    /// ```
+    pub const fn is_default(self) -> bool {
+        matches!(self, Source::Default)
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Source::File => "file",
+            Source::Env => "environment variable",
+            Source::Http => "URL",
+            Source::Cmd => "command",
+            Source::Provider => "secret provider",
+            Source::Git => "git repository",
+            Source::Clock => "current time",
+            Source::Default => "default",
+        };
+        f.write_str(s)
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+/// A string embedded with the `obfuscate` option, which is only reconstructed on first use
+///
+/// The bytes in the binary are XORed with a keystream, so the value doesn't show up in the output
+/// of tools like `strings`. This is not encryption: anyone with the binary can recover the value
+/// with a little effort.
+///
+/// ```
+/// use file_env_const_runtime::Obfuscated;
+///
+/// static SECRET: Obfuscated = Obfuscated::new(&[0xff, 0x16, 0x7e], 42);
+/// assert_eq!(&*SECRET, "abc");
+/// ```
+pub struct Obfuscated {
+    data: &'static [u8],
+    seed: u64,
+    value: OnceLock<String>,
+}
+
+impl Obfuscated {
+    /// Wraps `data` which was obfuscated with the keystream generated from `seed`
+    #[doc(hidden)]
+    pub const fn new(data: &'static [u8], seed: u64) -> Self {
+        Obfuscated {
+            data,
+            seed,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// Returns the original value, reconstructing it if this is the first use
+    pub fn get(&self) -> &str {
+        self.value.get_or_init(|| {
+            let mut data = self.data.to_vec();
+            xor_keystream(&mut data, self.seed);
+            String::from_utf8(data).expect("obfuscated data was valid UTF-8 when embedded")
+        })
+    }
+}
+
+impl Deref for Obfuscated {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.get()
+    }
+}
+
+impl fmt::Debug for Obfuscated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Obfuscated(..)")
+    }
+}
+
+/// XORs `data` in place with a keystream generated from `seed`
+///
+/// This is its own inverse, and is shared with the macros which do the obfuscation.
+#[doc(hidden)]
+pub fn xor_keystream(data: &mut [u8], seed: u64) {
+    // xorshift64* can't start from zero
+    let mut state = seed | 1;
+    for byte in data {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        *byte ^= (state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 56) as u8;
+    }
+}
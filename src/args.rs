@@ -0,0 +1,662 @@
+//! Parsing of macro arguments: string literals, followed by any options
+//!
+//! Options are either flags, such as `required`, or `name = value` pairs. The default can also be
+//! an expression rather than a string literal.
+//!
+//! ```text
+//! file_env!("prod-cert.pem", "PROD_CERT", required)
+//! file_env!("motd.txt", "MOTD", include_str!("default_motd.txt"))
+//! ```
+
+use std::path::PathBuf;
+
+use proc_macro2::{Group, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::{Brace, Paren};
+use syn::{parenthesized, Ident, LitBool, LitByteStr, LitInt, LitStr, Token};
+
+use crate::permissions::Policy;
+use crate::{config, sha256};
+
+/// The arguments to a macro invocation
+pub(crate) struct Args {
+    /// The positional string arguments, in order
+    pub(crate) values: Vec<LitStr>,
+    /// A braced block of code given as the default, for `file_env_include!`
+    pub(crate) code: Option<Group>,
+    /// String arguments for particular build profiles or targets, instead of `values`
+    pub(crate) branches: Vec<Branch>,
+    /// The fallback string used by every branch, given as `default = "..."`
+    pub(crate) default: Option<LitStr>,
+    /// An expression given as the default instead of a string, such as `concat!(...)` or a
+    /// constant
+    pub(crate) expression: Option<TokenStream>,
+    /// An expression given as `default = ...` for every branch, instead of a string
+    pub(crate) default_expression: Option<TokenStream>,
+    /// A byte string given as the default instead of a string, for the bytes macros
+    pub(crate) bytes: Option<LitByteStr>,
+    /// A byte string given as `default = b"..."` for every branch, for the bytes macros
+    pub(crate) default_bytes: Option<LitByteStr>,
+    pub(crate) options: Options,
+    /// The tokens the arguments were parsed from, which are passed on to the invocation resolving
+    /// the value in place of a `dev_placeholder`
+    pub(crate) tokens: TokenStream,
+}
+
+/// String arguments which are only used when building with a particular profile or target
+///
+/// ```text
+/// file_env!(debug = ("dev.toml", "DEV_CFG"), cfg(windows) = ("win.toml", "WIN_CFG"))
+/// file_env!(feature("telemetry") = ("telemetry.key", "TELEMETRY_KEY"), default = "")
+/// ```
+pub(crate) struct Branch {
+    /// The predicate choosing the branch, as it's written in `cfg!(...)`
+    pub(crate) condition: TokenStream,
+    pub(crate) values: Vec<LitStr>,
+    pub(crate) span: Span,
+}
+
+/// Options which modify how a value is resolved
+#[derive(Default, Clone)]
+pub(crate) struct Options {
+    /// Fail the build if the first source doesn't resolve
+    pub(crate) required: bool,
+    /// Don't print any fallback notices
+    pub(crate) quiet: bool,
+    /// Shown in the compile error when the value can't be resolved
+    pub(crate) message: Option<LitStr>,
+    /// Fail the build if a later source also exists and has a different value
+    pub(crate) verify_match: bool,
+    /// Embed this instead of resolving the value in builds with debug assertions
+    pub(crate) dev_placeholder: Option<LitStr>,
+    /// Never show the names of sources, replacing them with a hash
+    pub(crate) secret: bool,
+    /// Allow embedding files which look like private keys
+    pub(crate) allow_sensitive: bool,
+    /// Embed the value as a `file_env_const_runtime::Obfuscated` rather than a string literal
+    pub(crate) obfuscate: bool,
+    /// Encrypt the value with the key in this build time environment variable, embedding it as a
+    /// `file_env_const_runtime::Encrypted`
+    pub(crate) encrypt: Option<LitStr>,
+    /// Compress the value with this algorithm, embedding it as a
+    /// `file_env_const_runtime::Compressed`
+    pub(crate) compress: Option<LitStr>,
+    /// Store the value in a single static rather than a literal
+    pub(crate) intern: bool,
+    /// Store the bytes in a static placed in this linker section
+    pub(crate) section: Option<LitStr>,
+    /// Store the bytes in a static aligned to this many bytes
+    pub(crate) align: Option<LitInt>,
+    /// Check the value is in this format, embedding it as a `LazyLock` which deserializes it
+    pub(crate) deserialize: Option<LitStr>,
+    /// Fail the build unless the value is in this format
+    pub(crate) validate: Option<LitStr>,
+    /// The comma-separated schemes a URL can have with `validate = "url"`
+    pub(crate) schemes: Option<LitStr>,
+    /// Embed an RFC 3339 timestamp as the `u64` number of seconds since the Unix epoch
+    pub(crate) epoch: bool,
+    /// The hash algorithm used by `file_env_hash!`
+    pub(crate) algorithm: Option<LitStr>,
+    /// Fail the build unless the value has this hex SHA-256 digest
+    pub(crate) expect_sha256: Option<LitStr>,
+    /// Fail the build unless the value is signed by this minisign public key
+    pub(crate) minisign_key: Option<LitStr>,
+    /// The environment variable holding the minisign signature, instead of a `.sig` file
+    pub(crate) signature_env: Option<LitStr>,
+    /// The number of seconds to wait when fetching a URL
+    pub(crate) timeout: Option<LitInt>,
+    /// The piece of git metadata embedded by `git_env!`
+    pub(crate) field: Option<LitStr>,
+    /// How `build_time_env!` formats the time
+    pub(crate) format: Option<LitStr>,
+    /// Substitute `${VAR}` placeholders in the value from the build environment
+    pub(crate) template: bool,
+    /// Embed only this line of the value
+    pub(crate) line: Option<LitInt>,
+    /// Embed only these lines of the value
+    pub(crate) lines: Option<Range>,
+    /// Embed only a capture group of this regex's first match in the value
+    pub(crate) extract: Option<LitStr>,
+    /// Embed only the value of the `KEY=value` line with this key
+    pub(crate) key: Option<LitStr>,
+    /// Embed only the bytes from this offset
+    pub(crate) offset: Option<LitInt>,
+    /// Embed only this many bytes
+    pub(crate) length: Option<LitInt>,
+    /// Embed only the characters in this range, counting from 0
+    pub(crate) chars: Option<Range>,
+    /// Embed the value as bytes rather than a string, which is set by the bytes macros rather than
+    /// parsed
+    pub(crate) bytes: bool,
+    /// Parse the value as Rust tokens, which is set by `file_env_include!` rather than parsed
+    pub(crate) include: bool,
+    /// Read the size or modification time of files rather than their contents, and embed it as a
+    /// number, which is set by `file_meta_env!` rather than parsed
+    pub(crate) meta: bool,
+    /// The string `concat_env_file!` puts between its parts
+    pub(crate) separator: Option<LitStr>,
+    /// Remove blank lines, and lines starting with this comment marker
+    pub(crate) strip_comments: Option<LitStr>,
+    /// Compact the value as JSON or by collapsing whitespace
+    pub(crate) minify: Option<LitStr>,
+    /// Convert the value to this Unicode normalization form
+    pub(crate) normalize: Option<LitStr>,
+    /// Change the case of the value
+    pub(crate) transform: Option<LitStr>,
+    /// Merge the overlay file beside the file into it
+    pub(crate) merge: bool,
+    /// The overlay file merged into the file, instead of `<name>.local.<extension>`
+    pub(crate) overlay: Option<LitStr>,
+    /// Remove leading and trailing whitespace from the value
+    pub(crate) trim: bool,
+    /// Fail the build if the value is larger than this many bytes
+    pub(crate) max_size: Option<LitInt>,
+    /// Prepended to the name of every environment variable source
+    pub(crate) env_prefix: Option<LitStr>,
+    /// The name of the value for `FILE_ENV_CONST_OVERRIDE_<LABEL>`, instead of its environment
+    /// variable
+    pub(crate) label: Option<LitStr>,
+    /// Replace `\n` and `\r\n` escapes in environment variables with newlines
+    pub(crate) unescape_newlines: bool,
+    /// Replace `\t` escapes in environment variables with tabs
+    pub(crate) unescape_tabs: bool,
+    /// Fail the build unless the value is JSON matching the JSON Schema in this file
+    pub(crate) schema: Option<LitStr>,
+    /// Whether a source whose value fails a check is an error or skipped like a missing source
+    pub(crate) on_invalid: Option<LitStr>,
+    /// Whether files can be read through symlinks, which is true if not given
+    pub(crate) follow_symlinks: Option<LitBool>,
+    /// Look for relative filenames in each parent directory up to the workspace root too
+    pub(crate) search_up: bool,
+    /// The directory relative paths are resolved from, which is set by `file_env_const.toml`
+    /// rather than parsed
+    pub(crate) base_dir: PathBuf,
+    /// The directory files must be inside when symlinks aren't followed, which is set by
+    /// `file_env_const.toml` rather than parsed
+    pub(crate) root: PathBuf,
+    /// What to do when a secret file isn't protected, which is set by `file_env_const.toml`
+    /// rather than parsed
+    pub(crate) secret_permissions: Policy,
+    /// Forbid any fallback, which is set by `file_env_const.toml` rather than parsed
+    pub(crate) strict: bool,
+    /// The command template for `provider:` sources, which is set by `file_env_const.toml` rather
+    /// than parsed
+    #[cfg(feature = "cmd")]
+    pub(crate) provider: Option<String>,
+}
+
+/// A range of positions, written like a Rust range: `A..B`, `A..=B` or `A..`
+#[derive(Clone)]
+pub(crate) struct Range {
+    /// The first position in the range
+    pub(crate) start: usize,
+    /// The position after the end of the range, if it's bounded
+    pub(crate) end: Option<usize>,
+}
+
+impl Parse for Range {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let start: LitInt = input.parse()?;
+        let span = start.span();
+        let start = start.base10_parse()?;
+        let inclusive = if input.peek(Token![..=]) {
+            input.parse::<Token![..=]>()?;
+            true
+        } else {
+            input.parse::<Token![..]>()?;
+            false
+        };
+        let end = if input.peek(LitInt) {
+            let end: usize = input.parse::<LitInt>()?.base10_parse()?;
+            Some(if inclusive { end + 1 } else { end })
+        } else if inclusive {
+            return Err(input.error("an inclusive range needs an end"));
+        } else {
+            None
+        };
+        if end.is_some_and(|end| end <= start) {
+            return Err(syn::Error::new(span, "the range must not be empty"));
+        }
+        Ok(Range { start, end })
+    }
+}
+
+/// The arguments to `concat_env_file!`: parenthesised groups of string arguments, each resolved
+/// like the arguments to `env_file!`, followed by any options
+pub(crate) struct ConcatArgs {
+    pub(crate) parts: Vec<Vec<LitStr>>,
+    pub(crate) options: Options,
+}
+
+impl Parse for ConcatArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut parts = Vec::new();
+        let mut options = Options::from_config()?;
+        let mut seen_option = false;
+
+        while !input.is_empty() {
+            if input.peek(Paren) {
+                let content;
+                let paren = parenthesized!(content in input);
+                if seen_option {
+                    return Err(syn::Error::new(
+                        paren.span.join(),
+                        "sources must come before any options",
+                    ));
+                }
+                let part = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                parts.push(part.into_iter().collect());
+            } else {
+                let name: Ident = input.parse()?;
+                options.set(&name, input)?;
+                seen_option = true;
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(ConcatArgs { parts, options })
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let tokens = input.fork().parse()?;
+        let mut values = Vec::new();
+        let mut code: Option<Group> = None;
+        let mut branches = Vec::new();
+        let mut default = None;
+        let mut expression: Option<TokenStream> = None;
+        let mut default_expression = None;
+        let mut bytes: Option<LitByteStr> = None;
+        let mut default_bytes = None;
+        let mut options = Options::from_config()?;
+        let mut seen_option = false;
+
+        while !input.is_empty() {
+            if input.peek(LitStr) {
+                let value: LitStr = input.parse()?;
+                if seen_option || code.is_some() || !branches.is_empty() {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "string arguments must come before any options",
+                    ));
+                }
+                if expression.is_some() || bytes.is_some() {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "the default must be the last of the string arguments",
+                    ));
+                }
+                values.push(value);
+            } else if input.peek(LitByteStr) {
+                let value: LitByteStr = input.parse()?;
+                if seen_option || code.is_some() || expression.is_some() || bytes.is_some() {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "a default byte string must follow the string arguments",
+                    ));
+                }
+                if !branches.is_empty() {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "give the default for branches as `default = b\"...\"`",
+                    ));
+                }
+                bytes = Some(value);
+            } else if is_expression(input) {
+                let tokens = parse_expression(input)?;
+                if seen_option
+                    || code.is_some()
+                    || expression.is_some()
+                    || bytes.is_some()
+                    || !branches.is_empty()
+                {
+                    return Err(syn::Error::new_spanned(
+                        tokens,
+                        "a default expression must follow the string arguments",
+                    ));
+                }
+                expression = Some(tokens);
+            } else if input.peek(Brace) {
+                let block: Group = input.parse()?;
+                if seen_option || code.is_some() {
+                    return Err(syn::Error::new(
+                        block.span(),
+                        "a default block must follow the string arguments",
+                    ));
+                }
+                code = Some(block);
+            } else {
+                let name: Ident = input.parse()?;
+                match name.to_string().as_str() {
+                    "debug" | "release" | "cfg" | "feature" => {
+                        if seen_option || default.is_some() {
+                            return Err(syn::Error::new(
+                                name.span(),
+                                "branches must come before the default and any options",
+                            ));
+                        }
+                        branches.push(Branch::parse(&name, input)?);
+                    }
+                    "default" => {
+                        if seen_option {
+                            return Err(syn::Error::new(
+                                name.span(),
+                                "the default must come before any options",
+                            ));
+                        }
+                        input.parse::<Token![=]>()?;
+                        if input.peek(LitStr) {
+                            default = Some(input.parse()?);
+                        } else if input.peek(LitByteStr) {
+                            default_bytes = Some(input.parse()?);
+                        } else {
+                            default_expression = Some(parse_expression(input)?);
+                        }
+                    }
+                    _ => {
+                        options.set(&name, input)?;
+                        seen_option = true;
+                    }
+                }
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(Args {
+            values,
+            code,
+            branches,
+            default,
+            expression,
+            default_expression,
+            bytes,
+            default_bytes,
+            options,
+            tokens,
+        })
+    }
+}
+
+impl Branch {
+    /// Parses the rest of a branch starting with `name`, which is `debug`, `release`, `feature` or
+    /// `cfg`
+    fn parse(name: &Ident, input: ParseStream) -> syn::Result<Branch> {
+        let condition = match name.to_string().as_str() {
+            "debug" => quote!(debug_assertions),
+            "release" => quote!(not(debug_assertions)),
+            "feature" => {
+                let content;
+                parenthesized!(content in input);
+                let feature: LitStr = content.parse()?;
+                quote!(feature = #feature)
+            }
+            _ => {
+                let content;
+                parenthesized!(content in input);
+                content.parse()?
+            }
+        };
+        input.parse::<Token![=]>()?;
+        let content;
+        let paren = parenthesized!(content in input);
+        let values = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+        Ok(Branch {
+            condition,
+            values: values.into_iter().collect(),
+            span: paren.span.join(),
+        })
+    }
+}
+
+impl Options {
+    /// Returns the options set by default by the configuration file of the crate being built
+    pub(crate) fn from_config() -> syn::Result<Options> {
+        let config = config::load().map_err(|e| syn::Error::new(Span::call_site(), e))?;
+        let root = config::crate_root();
+        Ok(Options {
+            trim: config.trim,
+            max_size: config
+                .max_size
+                .map(|n| LitInt::new(&n.to_string(), Span::call_site())),
+            env_prefix: config
+                .env_prefix
+                .map(|prefix| LitStr::new(&prefix, Span::call_site())),
+            follow_symlinks: config
+                .follow_symlinks
+                .map(|follow| LitBool::new(follow, Span::call_site())),
+            base_dir: match &config.base_dir {
+                Some(dir) => root.join(dir),
+                None => root.clone(),
+            },
+            root: match &config.root {
+                Some(dir) => root.join(dir),
+                None => root,
+            },
+            strict: config.strict,
+            secret_permissions: config.secret_permissions,
+            #[cfg(feature = "cmd")]
+            provider: config.provider,
+            ..Options::default()
+        })
+    }
+
+    /// Sets the option `name`, parsing its value from `input`
+    pub(crate) fn set(&mut self, name: &Ident, input: ParseStream) -> syn::Result<()> {
+        match name.to_string().as_str() {
+            "required" => self.required = flag(name, input)?,
+            "quiet" => self.quiet = flag(name, input)?,
+            "message" => self.message = Some(value(input)?),
+            "verify_match" => self.verify_match = flag(name, input)?,
+            "dev_placeholder" => self.dev_placeholder = Some(value(input)?),
+            "secret" => self.secret = flag(name, input)?,
+            "allow_sensitive" => self.allow_sensitive = flag(name, input)?,
+            "obfuscate" => self.obfuscate = flag(name, input)?,
+            "encrypt" => self.encrypt = Some(value(input)?),
+            "compress" => self.compress = Some(value(input)?),
+            "intern" => self.intern = flag(name, input)?,
+            "section" => self.section = Some(value(input)?),
+            "align" => self.align = Some(value(input)?),
+            "deserialize" => self.deserialize = Some(value(input)?),
+            "validate" => self.validate = Some(value(input)?),
+            "schemes" => self.schemes = Some(value(input)?),
+            "epoch" => self.epoch = flag(name, input)?,
+            "algorithm" => self.algorithm = Some(value(input)?),
+            "expect_sha256" => self.expect_sha256 = Some(value(input)?),
+            "minisign_key" => self.minisign_key = Some(value(input)?),
+            "signature_env" => self.signature_env = Some(value(input)?),
+            "timeout" => self.timeout = Some(value(input)?),
+            "field" => self.field = Some(value(input)?),
+            "format" => self.format = Some(value(input)?),
+            "template" => self.template = flag(name, input)?,
+            "line" => self.line = Some(value(input)?),
+            "lines" => self.lines = Some(value(input)?),
+            "extract" => self.extract = Some(value(input)?),
+            "key" => self.key = Some(value(input)?),
+            "offset" => self.offset = Some(value(input)?),
+            "length" => self.length = Some(value(input)?),
+            "chars" => self.chars = Some(value(input)?),
+            "separator" => self.separator = Some(value(input)?),
+            "strip_comments" => self.strip_comments = Some(value(input)?),
+            "minify" => self.minify = Some(value(input)?),
+            "normalize" => self.normalize = Some(value(input)?),
+            "transform" => self.transform = Some(value(input)?),
+            "merge" => self.merge = flag(name, input)?,
+            "overlay" => self.overlay = Some(value(input)?),
+            "trim" => self.trim = flag(name, input)?,
+            "max_size" => self.max_size = Some(value(input)?),
+            "env_prefix" => self.env_prefix = Some(value(input)?),
+            "label" => self.label = Some(value(input)?),
+            "follow_symlinks" => self.follow_symlinks = Some(value(input)?),
+            "search_up" => self.search_up = flag(name, input)?,
+            "unescape_newlines" => self.unescape_newlines = flag(name, input)?,
+            "unescape_tabs" => self.unescape_tabs = flag(name, input)?,
+            "schema" => self.schema = Some(value(input)?),
+            "on_invalid" => self.on_invalid = Some(value(input)?),
+            _ => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("unknown option `{}`", name),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the value of the source which is found is changed before it's embedded
+    pub(crate) fn transforms(&self) -> bool {
+        self.offset.is_some()
+            || self.length.is_some()
+            || self.trim
+            || self.merges()
+            || self.transforms_text()
+    }
+
+    /// Returns true if an overlay is merged into a file which is found
+    pub(crate) fn merges(&self) -> bool {
+        self.merge || self.overlay.is_some()
+    }
+
+    /// Returns true if the value is changed in a way which treats it as text
+    pub(crate) fn transforms_text(&self) -> bool {
+        self.template
+            || self.strip_comments.is_some()
+            || self.minify.is_some()
+            || self.normalize.is_some()
+            || self.transform.is_some()
+            || self.chars.is_some()
+            || self.line.is_some()
+            || self.lines.is_some()
+            || self.key.is_some()
+            || self.extract.is_some()
+    }
+
+    /// Returns the first option given which embeds something other than a `&'static str`, such as
+    /// `obfuscate`, for the macros which define string constants
+    pub(crate) fn embeds_non_str(&self) -> Option<&'static str> {
+        let embedding = [
+            ("obfuscate", self.obfuscate),
+            ("encrypt", self.encrypt.is_some()),
+            ("compress", self.compress.is_some()),
+            ("deserialize", self.deserialize.is_some()),
+            ("epoch", self.epoch),
+        ];
+        embedding
+            .into_iter()
+            .find(|(_, requested)| *requested)
+            .map(|(option, _)| option)
+    }
+
+    /// Returns true if a source whose value fails `validate`, `schema` or `deserialize`, or which
+    /// isn't UTF-8 for a text macro, is skipped like a missing source, with `on_invalid = "fallback"`
+    pub(crate) fn falls_back_on_invalid(&self) -> syn::Result<bool> {
+        let Some(on_invalid) = &self.on_invalid else {
+            return Ok(false);
+        };
+        match on_invalid.value().as_str() {
+            "fallback" => Ok(true),
+            "error" => Ok(false),
+            v => Err(syn::Error::new(
+                on_invalid.span(),
+                format!("Unknown on_invalid {}, expected fallback or error", v),
+            )),
+        }
+    }
+
+    /// Returns the name of the environment variable `name` after the `env_prefix`
+    pub(crate) fn env_name(&self, name: &str) -> String {
+        let name = name.strip_prefix("env:").unwrap_or(name);
+        match &self.env_prefix {
+            Some(prefix) => format!("{}{}", prefix.value(), name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Returns the path or variable `name` as it may be shown in diagnostics and the manifest
+    pub(crate) fn redact(&self, name: String) -> String {
+        if self.secret {
+            format!("<secret {}>", &sha256::hex_digest(name.as_bytes())[..12])
+        } else {
+            name
+        }
+    }
+}
+
+/// Parses the `= value` part of an option
+fn value<T: Parse>(input: ParseStream) -> syn::Result<T> {
+    input.parse::<Token![=]>()?;
+    input.parse()
+}
+
+/// Returns true if the next argument is an expression rather than a string or an option: a macro
+/// call such as `concat!(...)`, a path such as `crate::DEFAULT`, or a constant such as `DEFAULT`
+///
+/// Option names are lower case, so a single identifier is only a constant if it has an upper case
+/// letter.
+fn is_expression(input: ParseStream) -> bool {
+    if input.peek(Token![::]) || input.peek(Token![&]) {
+        return true;
+    }
+    let fork = input.fork();
+    let Ok(ident) = fork.parse::<Ident>() else {
+        return false;
+    };
+    fork.peek(Token![!])
+        || fork.peek(Token![::])
+        || ident.to_string().chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// Parses the tokens of an expression, up to the next comma outside any brackets
+fn parse_expression(input: ParseStream) -> syn::Result<TokenStream> {
+    let mut tokens = TokenStream::new();
+    while !input.is_empty() && !input.peek(Token![,]) {
+        tokens.extend([input.parse::<TokenTree>()?]);
+    }
+    if tokens.is_empty() {
+        return Err(input.error("expected a default string or expression"));
+    }
+    Ok(tokens)
+}
+
+/// Parses a flag, which doesn't take a value
+fn flag(name: &Ident, input: ParseStream) -> syn::Result<bool> {
+    if input.peek(Token![=]) {
+        return Err(syn::Error::new(
+            name.span(),
+            format!("`{}` is a flag, and doesn't take a value", name),
+        ));
+    }
+    Ok(true)
+}
+
+/// Returns `tokens` without the `dev_placeholder = "..."` option and its comma, for the invocation
+/// which resolves the value in builds without debug assertions
+pub(crate) fn without_placeholder(tokens: TokenStream) -> TokenStream {
+    let mut tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    let is_punct = |token: Option<&TokenTree>, c: char| matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == c);
+    let start = tokens.iter().enumerate().position(|(i, token)| {
+        matches!(token, TokenTree::Ident(ident) if ident == "dev_placeholder")
+            && is_punct(tokens.get(i + 1), '=')
+    });
+    if let Some(start) = start {
+        let (start, end) = if start > 0 && is_punct(tokens.get(start - 1), ',') {
+            (start - 1, start + 3)
+        } else if is_punct(tokens.get(start + 3), ',') {
+            (start, start + 4)
+        } else {
+            (start, start + 3)
+        };
+        tokens.drain(start..end.min(tokens.len()));
+    }
+    tokens.into_iter().collect()
+}
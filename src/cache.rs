@@ -0,0 +1,47 @@
+//! A cache of file contents, shared by every invocation in a compilation
+//!
+//! Each crate is compiled by a single process, which keeps the procedural macro loaded for every
+//! invocation. Entries are keyed on the path, and the modification time and length which the file
+//! had when it was read, so an edited file is always read again.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::track;
+
+struct Entry {
+    modified: SystemTime,
+    len: u64,
+    content: Arc<[u8]>,
+}
+
+static CACHE: OnceLock<Mutex<HashMap<PathBuf, Entry>>> = OnceLock::new();
+
+/// Reads the file at `path`, reusing an earlier read if the file hasn't changed
+pub(crate) fn read(path: &Path) -> io::Result<Arc<[u8]>> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata.modified()?;
+    let len = metadata.len();
+    track::path(path);
+
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(entry) = cache.lock().unwrap().get(path) {
+        if entry.modified == modified && entry.len == len {
+            return Ok(Arc::clone(&entry.content));
+        }
+    }
+
+    let content: Arc<[u8]> = std::fs::read(path)?.into();
+    cache.lock().unwrap().insert(
+        path.to_path_buf(),
+        Entry {
+            modified,
+            len,
+            content: Arc::clone(&content),
+        },
+    );
+    Ok(content)
+}
@@ -0,0 +1,119 @@
+//! Running `cmd:` and `provider:` sources, with the `cmd` feature
+//!
+//! The command is split on whitespace and run directly, without a shell, from the directory of the
+//! crate being built. A command which can't be run, exits unsuccessfully or doesn't print UTF-8 is
+//! treated like a missing file, so the next source is tried.
+//!
+//! A `provider:` source runs the command template in `FILE_ENV_CONST_PROVIDER`, or the `provider`
+//! in `file_env_const.toml` if that isn't set, such as `vault kv get -field={key} {path}`. For
+//! `provider:ci/api_token`, `{path}` is replaced by `ci`, `{key}` by `api_token` and `{name}` by
+//! `ci/api_token`.
+
+use syn::LitStr;
+
+use crate::args::Options;
+use crate::Kind;
+
+/// The environment variable holding the command template for `provider:` sources
+#[cfg(feature = "cmd")]
+const PROVIDER_VAR: &str = "FILE_ENV_CONST_PROVIDER";
+
+/// Runs the command in `arg` and returns its stdout, without a single trailing newline
+#[cfg(feature = "cmd")]
+pub(crate) fn read(arg: &LitStr) -> syn::Result<Kind> {
+    let value = arg.value();
+    let command = value.strip_prefix("cmd:").unwrap_or(&value).trim();
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if words.is_empty() {
+        return Err(syn::Error::new(
+            arg.span(),
+            "A `cmd:` source needs a command to run",
+        ));
+    }
+    Ok(run(&words, command.to_string(), arg))
+}
+
+/// Runs the provider's command template for the secret named in `arg`
+#[cfg(feature = "cmd")]
+pub(crate) fn read_provider(arg: &LitStr, options: &Options) -> syn::Result<Kind> {
+    let value = arg.value();
+    let name = value.strip_prefix("provider:").unwrap_or(&value).trim();
+    let (template, setting) = match crate::track::var(PROVIDER_VAR) {
+        Ok(template) => (template, PROVIDER_VAR),
+        Err(_) => match &options.provider {
+            Some(template) => (template.clone(), "`provider` in file_env_const.toml"),
+            None => {
+                return Err(syn::Error::new(
+                    arg.span(),
+                    format!(
+                        "A `provider:` source needs {} or `provider` in file_env_const.toml set \
+                         to a command template, such as `vault kv get -field={{key}} {{path}}`",
+                        PROVIDER_VAR
+                    ),
+                ))
+            }
+        },
+    };
+
+    let (path, key) = name.rsplit_once('/').unwrap_or(("", name));
+    let words: Vec<String> = template
+        .split_whitespace()
+        .map(|word| {
+            word.replace("{name}", name)
+                .replace("{path}", path)
+                .replace("{key}", key)
+        })
+        .collect();
+    if words.is_empty() {
+        return Err(syn::Error::new(
+            arg.span(),
+            format!("{} is set to an empty command", setting),
+        ));
+    }
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    Ok(run(&words, name.to_string(), arg))
+}
+
+/// Runs the program `words[0]` with the rest of `words` as arguments, returning its stdout as the
+/// value found at `name`
+#[cfg(feature = "cmd")]
+fn run(words: &[&str], name: String, arg: &LitStr) -> Kind {
+    let mut process = std::process::Command::new(words[0]);
+    process.args(&words[1..]);
+    if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        process.current_dir(dir);
+    }
+    let stdout = process
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    match stdout {
+        Some(mut stdout) => {
+            if stdout.ends_with('\n') {
+                stdout.pop();
+                if stdout.ends_with('\r') {
+                    stdout.pop();
+                }
+            }
+            Kind::Data(crate::Data::text(&stdout, arg.span()), name)
+        }
+        None => Kind::Name(name, arg.span()),
+    }
+}
+
+#[cfg(not(feature = "cmd"))]
+pub(crate) fn read(arg: &LitStr) -> syn::Result<Kind> {
+    Err(syn::Error::new(
+        arg.span(),
+        "Running a command requires the `cmd` feature",
+    ))
+}
+
+#[cfg(not(feature = "cmd"))]
+pub(crate) fn read_provider(arg: &LitStr, _options: &Options) -> syn::Result<Kind> {
+    Err(syn::Error::new(
+        arg.span(),
+        "A `provider:` source requires the `cmd` feature",
+    ))
+}
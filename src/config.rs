@@ -0,0 +1,173 @@
+//! Reading `file_env_const.toml` from the root of the crate being built, which sets defaults for
+//! every invocation in the crate
+//!
+//! ```text
+//! # Relative paths are resolved from `config/` rather than the crate root
+//! base_dir = "config"
+//! strict = true
+//! trim = true
+//! max_size = 65536
+//! env_prefix = "MYAPP_"
+//! follow_symlinks = false
+//! root = ".."
+//! secret_permissions = "error"
+//! provider = "vault kv get -field={key} {path}"
+//! ```
+//!
+//! The file is a flat list of `key = value` lines, where a value is a string, `true`, `false` or
+//! an integer, so it's parsed here rather than with a TOML library.
+
+use std::path::PathBuf;
+
+use crate::cache;
+use crate::permissions::Policy;
+
+/// The name of the configuration file, in the root of the crate being built
+const FILE_NAME: &str = "file_env_const.toml";
+
+/// The settings in the configuration file, which are all optional
+#[derive(Default)]
+pub(crate) struct Config {
+    /// The directory relative paths are resolved from, relative to the crate root
+    pub(crate) base_dir: Option<String>,
+    /// Turn every fallback into a compile error, like strict mode
+    pub(crate) strict: bool,
+    /// Trim whitespace from every value, like the `trim` option
+    pub(crate) trim: bool,
+    /// Fail the build if a value is larger than this many bytes, like the `max_size` option
+    pub(crate) max_size: Option<u64>,
+    /// Prepended to the name of every environment variable source, like the `env_prefix` option
+    pub(crate) env_prefix: Option<String>,
+    /// Whether files can be read through symlinks, like the `follow_symlinks` option
+    pub(crate) follow_symlinks: Option<bool>,
+    /// The directory files must be inside when symlinks aren't followed, relative to the crate
+    /// root
+    pub(crate) root: Option<String>,
+    /// What to do when a file embedded with the `secret` option isn't protected
+    pub(crate) secret_permissions: Policy,
+    /// The command template run for `provider:` sources, unless `FILE_ENV_CONST_PROVIDER` is set
+    pub(crate) provider: Option<String>,
+}
+
+/// A value in the configuration file
+enum Value {
+    String(String),
+    Bool(bool),
+    Integer(u64),
+}
+
+/// Returns the root of the crate being built, which relative paths are resolved from by default
+pub(crate) fn crate_root() -> PathBuf {
+    std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+}
+
+/// Returns the root of the workspace the crate being built is in, which is the nearest directory
+/// at or above the crate root whose `Cargo.toml` has a `[workspace]` table, or the crate root if
+/// there's none
+pub(crate) fn workspace_root() -> PathBuf {
+    let root = crate_root();
+    let workspace = root.ancestors().find(|dir| {
+        cache::read(&dir.join("Cargo.toml")).is_ok_and(|manifest| {
+            String::from_utf8_lossy(&manifest)
+                .lines()
+                .any(|line| line.trim() == "[workspace]")
+        })
+    });
+    workspace.map_or(root.clone(), PathBuf::from)
+}
+
+/// Reads the configuration file of the crate being built, if it has one
+pub(crate) fn load() -> Result<Config, String> {
+    let path = crate_root().join(FILE_NAME);
+    let Ok(contents) = cache::read(&path) else {
+        return Ok(Config::default());
+    };
+    let contents = std::str::from_utf8(&contents)
+        .map_err(|_| format!("{} isn't valid UTF-8", path.display()))?;
+    parse(contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse(contents: &str) -> Result<Config, String> {
+    let mut config = Config::default();
+    for (i, line) in contents.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {}", i + 1, message);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected `key = value`"))?;
+        let key = key.trim();
+        let value = parse_value(value.trim()).map_err(|e| error(&e))?;
+        match (key, value) {
+            ("base_dir", Value::String(s)) => config.base_dir = Some(s),
+            ("env_prefix", Value::String(s)) => config.env_prefix = Some(s),
+            ("root", Value::String(s)) => config.root = Some(s),
+            ("provider", Value::String(s)) => config.provider = Some(s),
+            ("secret_permissions", Value::String(s)) => {
+                config.secret_permissions = Policy::parse(&s).map_err(|e| error(&e))?
+            }
+            ("follow_symlinks", Value::Bool(b)) => config.follow_symlinks = Some(b),
+            ("strict", Value::Bool(b)) => config.strict = b,
+            ("trim", Value::Bool(b)) => config.trim = b,
+            ("max_size", Value::Integer(n)) => config.max_size = Some(n),
+            ("base_dir" | "env_prefix" | "root" | "secret_permissions" | "provider", _) => {
+                return Err(error(&format!("`{}` must be a string", key)))
+            }
+            ("strict" | "trim" | "follow_symlinks", _) => {
+                return Err(error(&format!("`{}` must be true or false", key)))
+            }
+            ("max_size", _) => return Err(error("`max_size` must be a number of bytes")),
+            _ => {
+                return Err(error(&format!(
+                    "unknown setting `{}`, expected base_dir, strict, trim, max_size, env_prefix, \
+                     follow_symlinks, root, secret_permissions or provider",
+                    key
+                )))
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Parses a value, ignoring a comment after it
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    let after = chars.as_str().trim();
+                    if !after.is_empty() && !after.starts_with('#') {
+                        return Err("unexpected content after the string".to_string());
+                    }
+                    return Ok(Value::String(out));
+                }
+                '\\' => match chars.next() {
+                    Some('"') => out.push('"'),
+                    Some('\\') => out.push('\\'),
+                    Some('n') => out.push('\n'),
+                    Some('t') => out.push('\t'),
+                    _ => return Err("invalid escape in string".to_string()),
+                },
+                c => out.push(c),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+
+    let value = value.split('#').next().unwrap_or_default().trim();
+    match value {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        _ => value
+            .replace('_', "")
+            .parse()
+            .map(Value::Integer)
+            .map_err(|_| format!("expected a string, true, false or a number, not {}", value)),
+    }
+}
@@ -0,0 +1,195 @@
+//! Defining several constants, each with its own chain of sources, for `file_env_consts!`
+//!
+//! Each item is `NAME: env "VAR" | file "path" | "default", options;`, with the sources tried in
+//! the order they're written and an optional default string at the end. An item is resolved like
+//! the equivalent `file_env!` or `env_file!` invocation, so overrides, strict mode and the audit
+//! manifest work the same way.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::buffer::Cursor;
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Ident, LitStr, Token, Visibility};
+
+use crate::args::{Args, Options};
+use crate::{resolve_branches, Resolution, Resolved, Source};
+
+/// The items of a `file_env_consts!` block
+struct Items(Vec<Item>);
+
+/// A constant and the sources its value is read from
+struct Item {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    /// Each source's kind and its argument, the filename or variable name
+    chain: Vec<(Source, LitStr)>,
+    default: Option<LitStr>,
+    options: Options,
+    /// The tokens the item was parsed from
+    tokens: TokenStream,
+}
+
+impl Parse for Items {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(Items(items))
+    }
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let begin = input.cursor();
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+
+        let mut chain = Vec::new();
+        let mut default = None;
+        loop {
+            if input.peek(LitStr) {
+                let value: LitStr = input.parse()?;
+                if chain.is_empty() {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "Give at least one source before the default, such as `env \"VAR\"`",
+                    ));
+                }
+                default = Some(value);
+                break;
+            }
+            let kind: Ident = input.parse().map_err(|e| {
+                syn::Error::new(
+                    e.span(),
+                    "expected a source, such as `env \"API_URL\"` or `file \"api_url.txt\"`, or a \
+                     default string",
+                )
+            })?;
+            let source = match kind.to_string().as_str() {
+                "env" => Source::Env,
+                "file" => Source::File,
+                k => {
+                    return Err(syn::Error::new(
+                        kind.span(),
+                        format!("Unknown source {}, expected env or file", k),
+                    ))
+                }
+            };
+            chain.push((source, input.parse()?));
+            if input.parse::<Option<Token![|]>>()?.is_none() {
+                break;
+            }
+        }
+
+        let mut options = Options::from_config()?;
+        while input.parse::<Option<Token![,]>>()?.is_some() {
+            let name: Ident = input.parse()?;
+            options.set(&name, input)?;
+        }
+        input.parse::<Token![;]>()?;
+        Ok(Item {
+            attrs,
+            vis,
+            name,
+            chain,
+            default,
+            options,
+            tokens: tokens_between(begin, input.cursor()),
+        })
+    }
+}
+
+/// Returns the tokens from `begin` up to `end`
+fn tokens_between(begin: Cursor, end: Cursor) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    let mut cursor = begin;
+    while cursor != end {
+        let Some((token, next)) = cursor.token_tree() else {
+            break;
+        };
+        tokens.extend([token]);
+        cursor = next;
+    }
+    tokens
+}
+
+/// Expands to a `&'static str` constant for each item in `input`
+///
+/// Every item is resolved even if an earlier one fails, so all of their errors are reported.
+pub(crate) fn expand(input: proc_macro::TokenStream) -> syn::Result<TokenStream> {
+    let Items(items) = syn::parse(input)?;
+    let mut consts = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for item in items {
+        match expand_item(item) {
+            Ok(tokens) => consts.push(tokens),
+            Err(e) => match &mut errors {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(quote!(#(#consts)*)),
+    }
+}
+
+/// Expands a single item to its constant
+fn expand_item(item: Item) -> syn::Result<TokenStream> {
+    let Item {
+        attrs,
+        vis,
+        name,
+        chain,
+        default,
+        options,
+        tokens,
+    } = item;
+    if let Some(option) = options.embeds_non_str() {
+        return Err(syn::Error::new(
+            name.span(),
+            format!(
+                "`{}` can't be used with file_env_consts!, as its constants are `&'static str`",
+                option
+            ),
+        ));
+    }
+    let (sources, mut values): (Vec<Source>, Vec<LitStr>) = chain.into_iter().unzip();
+    values.extend(default);
+    let args = Args {
+        values,
+        code: None,
+        branches: Vec::new(),
+        default: None,
+        expression: None,
+        default_expression: None,
+        bytes: None,
+        default_bytes: None,
+        options,
+        tokens,
+    };
+    match resolve_branches("file_env_consts", args, &sources)? {
+        Resolution::Placeholder(placeholder, invocation) => {
+            let value = placeholder.value()?;
+            Ok(quote! {
+                #(#attrs)*
+                #[cfg(debug_assertions)]
+                #vis const #name: &'static str = #value;
+                #[cfg(not(debug_assertions))]
+                #invocation
+            })
+        }
+        resolution => {
+            let value = resolution.embed(Resolved::value)?;
+            Ok(quote! {
+                #(#attrs)*
+                #vis const #name: &'static str = #value;
+            })
+        }
+    }
+}
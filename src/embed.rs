@@ -0,0 +1,490 @@
+//! Turning a resolved value into the tokens which embed it
+//!
+//! By default this is just a string literal, but the `obfuscate`, `encrypt` and `compress` options
+//! instead expand to types from `file_env_const_runtime` which recover the value at runtime,
+//! `deserialize` expands to a `LazyLock` which deserializes it, `epoch` expands to a number, and
+//! `intern` stores the value in a static. The bytes macros embed a byte string literal instead,
+//! or a static in a linker section with `section`, or aligned with `align`.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::quote;
+use syn::{LitByteStr, LitStr};
+
+use crate::args::Options;
+use crate::Data;
+use crate::{sha256, time};
+
+/// Values larger than this many bytes are included from a file rather than inlined as a literal,
+/// which is much faster for rustc to handle
+const INLINE_LIMIT: usize = 1 << 20;
+
+/// Returns the tokens to embed `data` according to `options`
+///
+/// `path` is the file `data` was read from unchanged, if it was
+pub(crate) fn embed(
+    data: &LitStr,
+    path: Option<&Path>,
+    options: &Options,
+) -> syn::Result<TokenStream> {
+    if let Some(section) = &options.section {
+        return Err(syn::Error::new(
+            section.span(),
+            "`section` is only used by the bytes macros",
+        ));
+    }
+    if let Some(align) = &options.align {
+        return Err(syn::Error::new(
+            align.span(),
+            "`align` is only used by the bytes macros",
+        ));
+    }
+    if options.meta {
+        return number(data, options);
+    }
+    let requested = [
+        options.obfuscate,
+        options.encrypt.is_some(),
+        options.compress.is_some(),
+        options.intern,
+        options.deserialize.is_some(),
+        options.epoch,
+    ];
+    if requested.iter().filter(|&&r| r).count() > 1 {
+        return Err(syn::Error::new(
+            data.span(),
+            "Only one of `obfuscate`, `encrypt`, `compress`, `intern`, `deserialize` and `epoch` \
+             can be used at once",
+        ));
+    }
+
+    if let Some(key_var) = &options.encrypt {
+        encrypt(data, key_var)
+    } else if let Some(format) = &options.compress {
+        compress(data, format)
+    } else if options.obfuscate {
+        Ok(obfuscate(data))
+    } else if options.intern {
+        let shared = intern(data.value().as_bytes(), data.span(), path);
+        // SAFETY: the static is defined by an invocation interning the same bytes, which were taken
+        // from a `str`
+        Ok(quote!(unsafe { ::core::str::from_utf8_unchecked(#shared) }))
+    } else if let Some(format) = &options.deserialize {
+        deserialize(data, path, format, options.secret)
+    } else if options.epoch {
+        epoch(data, options.secret)
+    } else if let Some(path) = large_file(data.value().as_bytes(), path) {
+        let path = path.to_string_lossy();
+        Ok(quote!(::core::include_str!(#path)))
+    } else {
+        Ok(quote!(#data))
+    }
+}
+
+/// Returns the tokens to embed `data` as a `&'static [u8]`, for the bytes macros
+///
+/// `path` is the file `data` was read from unchanged, if it was
+pub(crate) fn embed_bytes(
+    data: &Data,
+    path: Option<&Path>,
+    options: &Options,
+) -> syn::Result<TokenStream> {
+    let text_only = [
+        ("obfuscate", options.obfuscate),
+        ("encrypt", options.encrypt.is_some()),
+        ("compress", options.compress.is_some()),
+        ("deserialize", options.deserialize.is_some()),
+        ("epoch", options.epoch),
+    ];
+    if let Some((option, _)) = text_only.iter().find(|(_, requested)| *requested) {
+        return Err(syn::Error::new(
+            data.span,
+            format!("`{}` can't be used with the bytes macros", option),
+        ));
+    }
+
+    let bytes = match large_file(&data.bytes, path) {
+        Some(path) => {
+            let path = path.to_string_lossy();
+            quote!(::core::include_bytes!(#path))
+        }
+        None => {
+            let bytes = LitByteStr::new(&data.bytes, data.span);
+            quote!(#bytes)
+        }
+    };
+    let mut attrs = TokenStream::new();
+    if let Some(section) = &options.section {
+        if section.value().is_empty() {
+            return Err(syn::Error::new(
+                section.span(),
+                "`section` must name a linker section",
+            ));
+        }
+        // `#[used]` keeps the static even if nothing refers to it, as for a section read by a
+        // bootloader or another image
+        attrs = quote! {
+            #[unsafe(link_section = #section)]
+            #[used]
+        };
+    }
+    let len = data.bytes.len();
+    if let Some(lit) = &options.align {
+        let align: usize = lit.base10_parse()?;
+        if !align.is_power_of_two() || align > 1 << 29 {
+            return Err(syn::Error::new(
+                lit.span(),
+                "`align` must be a power of two, at most 2^29",
+            ));
+        }
+        let align = Literal::usize_unsuffixed(align);
+        Ok(quote! {
+            {
+                #[repr(C, align(#align))]
+                struct Aligned<T>(T);
+                #attrs
+                static ALIGNED: Aligned<[u8; #len]> = Aligned(*#bytes);
+                &ALIGNED.0
+            }
+        })
+    } else if options.section.is_some() {
+        Ok(quote! {
+            {
+                #attrs
+                static PLACED: [u8; #len] = *#bytes;
+                &PLACED
+            }
+        })
+    } else if options.intern {
+        Ok(intern(&data.bytes, data.span, path))
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// If `data` is too large to inline, returns a file containing exactly `data`
+///
+/// This is a copy in `OUT_DIR` named after the hash of the content when there's a build script,
+/// and otherwise the file `data` was read from, if any.
+fn large_file(data: &[u8], path: Option<&Path>) -> Option<PathBuf> {
+    if data.len() <= INLINE_LIMIT {
+        return None;
+    }
+
+    if let Ok(out_dir) = std::env::var("OUT_DIR") {
+        let dir = PathBuf::from(out_dir).join("file_env_const");
+        let blob = dir.join(sha256::hex_digest(data));
+        if blob.exists()
+            || std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&blob, data))
+                .is_ok()
+        {
+            return Some(blob);
+        }
+    }
+    path.map(Path::to_path_buf)
+}
+
+/// Stores `bytes` in a single static, returning a `&'static [u8; N]` which every use of the value,
+/// and every other interned value with the same content in the crate, points at
+///
+/// A literal in a constant can otherwise be copied into each codegen unit the constant is used in.
+/// The static is named after the hash of the content and the crate, and defined by the first
+/// invocation in the compilation to intern that content, while each invocation declares it as an
+/// extern static. A string and a byte string with the same content share it.
+fn intern(bytes: &[u8], span: Span, path: Option<&Path>) -> TokenStream {
+    let len = bytes.len();
+    let mut hashed = crate_salt().as_bytes().to_vec();
+    hashed.extend_from_slice(bytes);
+    let symbol = format!(
+        "__file_env_const_interned_{}",
+        &sha256::hex_digest(&hashed)[..32]
+    );
+
+    let first = DEFINED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .insert(symbol.clone());
+    let definition = first.then(|| {
+        let bytes = match large_file(bytes, path) {
+            Some(path) => {
+                let path = path.to_string_lossy();
+                quote!(::core::include_bytes!(#path))
+            }
+            None => {
+                let bytes = LitByteStr::new(bytes, span);
+                quote!(#bytes)
+            }
+        };
+        quote! {
+            #[unsafe(export_name = #symbol)]
+            static INTERNED: [u8; #len] = *#bytes;
+        }
+    });
+    quote! {
+        {
+            #definition
+            unsafe extern "Rust" {
+                #[link_name = #symbol]
+                static SHARED: [u8; #len];
+            }
+            // SAFETY: the static is defined by an invocation interning the same bytes
+            unsafe { &SHARED }
+        }
+    }
+}
+
+/// The symbols of the interned statics defined so far in this compilation
+static DEFINED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+/// Returns the text which distinguishes the crate being compiled from the crates it links, such as
+/// the library a binary or doctest uses, so that they don't define the same symbol
+///
+/// This is the package and target, and the call site of the first value interned in the
+/// compilation, as a binary and its library, or a library and its doctests, can share the rest.
+fn crate_salt() -> &'static str {
+    static SALT: OnceLock<String> = OnceLock::new();
+    SALT.get_or_init(|| {
+        let call_site = proc_macro::Span::call_site();
+        let mut salt: Vec<String> = [
+            "CARGO_PKG_NAME",
+            "CARGO_PKG_VERSION",
+            "CARGO_CRATE_NAME",
+            "CARGO_BIN_NAME",
+        ]
+        .iter()
+        .map(|var| std::env::var(var).unwrap_or_default())
+        .collect();
+        salt.push(format!(
+            "{}:{}:{}",
+            call_site.file(),
+            call_site.line(),
+            call_site.column()
+        ));
+        salt.push(String::new());
+        salt.join("\0")
+    })
+}
+
+/// Checks that `data` is valid in the format named by `format`, and embeds it as a `LazyLock`
+/// which deserializes it on first use
+///
+/// The type to deserialize into is only known from where the expansion is used, so it can only be
+/// checked when it's first used.
+fn deserialize(
+    data: &LitStr,
+    path: Option<&Path>,
+    format: &LitStr,
+    secret: bool,
+) -> syn::Result<TokenStream> {
+    let function = deserializer(&data.value(), data.span(), format, secret)?;
+    let value = match large_file(data.value().as_bytes(), path) {
+        Some(path) => {
+            let path = path.to_string_lossy();
+            quote!(::core::include_str!(#path))
+        }
+        None => quote!(#data),
+    };
+    Ok(quote! {
+        ::std::sync::LazyLock::<_, fn() -> _>::new(|| ::file_env_const_runtime::#function(#value))
+    })
+}
+
+/// Checks that `value`, at `span`, can be deserialized as `format`, and returns the runtime function
+/// which deserializes it
+pub(crate) fn deserializer(
+    value: &str,
+    span: Span,
+    format: &LitStr,
+    secret: bool,
+) -> syn::Result<TokenStream> {
+    let invalid = |name: &str, e: String| {
+        syn::Error::new(
+            span,
+            format!("The value can't be deserialized as {}: {}", name, e),
+        )
+    };
+    match format.value().as_str() {
+        "json" => {
+            crate::json::parse(value).map_err(|e| invalid("JSON", e))?;
+            Ok(quote!(from_json))
+        }
+        "toml" => {
+            check_toml(value, format, secret)?.map_err(|e| invalid("TOML", e))?;
+            Ok(quote!(from_toml))
+        }
+        f => Err(syn::Error::new(
+            format.span(),
+            format!("Unknown format {}, expected json or toml", f),
+        )),
+    }
+}
+
+/// Returns whether `value` is a valid TOML document, or an error if TOML can't be checked
+///
+/// The reason a document is invalid quotes the line it's on, so it's only a general description
+/// if the value is `secret`.
+#[cfg(feature = "toml")]
+fn check_toml(value: &str, _format: &LitStr, secret: bool) -> syn::Result<Result<(), String>> {
+    Ok(value.parse::<toml::Table>().map(|_| ()).map_err(|e| {
+        if secret {
+            "the document isn't valid TOML".to_string()
+        } else {
+            e.to_string()
+        }
+    }))
+}
+
+#[cfg(not(feature = "toml"))]
+fn check_toml(_value: &str, format: &LitStr, _secret: bool) -> syn::Result<Result<(), String>> {
+    Err(syn::Error::new(
+        format.span(),
+        "`deserialize = \"toml\"` requires the `toml` feature",
+    ))
+}
+
+/// Embeds the RFC 3339 timestamp `data` as a `u64` number of seconds since the Unix epoch
+fn epoch(data: &LitStr, secret: bool) -> syn::Result<TokenStream> {
+    let secs = time::parse_rfc3339(data.value().trim(), secret)
+        .map_err(|e| syn::Error::new(data.span(), e))?;
+    let secs = u64::try_from(secs).map_err(|_| {
+        syn::Error::new(
+            data.span(),
+            "The timestamp is before the Unix epoch, so it can't be embedded as a `u64`",
+        )
+    })?;
+    Ok(quote!(#secs))
+}
+
+/// Embeds `data` as a `u64`, for `file_meta_env!`
+fn number(data: &LitStr, options: &Options) -> syn::Result<TokenStream> {
+    let embedding = [
+        ("obfuscate", options.obfuscate),
+        ("encrypt", options.encrypt.is_some()),
+        ("compress", options.compress.is_some()),
+        ("intern", options.intern),
+        ("deserialize", options.deserialize.is_some()),
+        ("epoch", options.epoch),
+    ];
+    if let Some((option, _)) = embedding.iter().find(|(_, requested)| *requested) {
+        return Err(syn::Error::new(
+            data.span(),
+            format!("`{}` can't be used with file_meta_env!", option),
+        ));
+    }
+    let value = data.value();
+    let n: u64 = value.trim().parse().map_err(|_| {
+        let shown = if options.secret {
+            "The value".to_string()
+        } else {
+            format!("{:?}", value)
+        };
+        syn::Error::new(
+            data.span(),
+            format!(
+                "{} isn't a number, so it can't be embedded as a `u64`",
+                shown
+            ),
+        )
+    })?;
+    Ok(quote!(#n))
+}
+
+fn obfuscate(data: &LitStr) -> TokenStream {
+    let mut bytes = data.value().into_bytes();
+    let call_site = proc_macro::Span::call_site();
+    let seed_input = format!(
+        "{}:{}:{}:{}",
+        call_site.file(),
+        call_site.line(),
+        call_site.column(),
+        data.value()
+    );
+    let digest = sha256::digest(seed_input.as_bytes());
+    let seed = u64::from_le_bytes(digest[..8].try_into().unwrap());
+    file_env_const_runtime::xor_keystream(&mut bytes, seed);
+    let bytes = LitByteStr::new(&bytes, data.span());
+    quote!(::file_env_const_runtime::Obfuscated::new(#bytes, #seed))
+}
+
+/// Encrypts `data` with the key in the build time environment variable named by `key_var`
+#[cfg(feature = "encrypt")]
+fn encrypt(data: &LitStr, key_var: &LitStr) -> syn::Result<TokenStream> {
+    let key = crate::track::var(&key_var.value())
+        .ok()
+        .and_then(|key| file_env_const_runtime::parse_hex_key(&key))
+        .ok_or_else(|| {
+            syn::Error::new(
+                key_var.span(),
+                format!(
+                    "The environment variable {} must be set to a key of 64 hex digits to encrypt",
+                    key_var.value()
+                ),
+            )
+        })?;
+
+    let plaintext = data.value().into_bytes();
+    let mut nonce_input = key.to_vec();
+    nonce_input.extend_from_slice(&plaintext);
+    let mut nonce = [0; 12];
+    nonce.copy_from_slice(&sha256::digest(&nonce_input)[..12]);
+
+    let ciphertext = file_env_const_runtime::encrypt(&plaintext, &key, &nonce);
+    let ciphertext = LitByteStr::new(&ciphertext, data.span());
+    Ok(quote!(::file_env_const_runtime::Encrypted::new(#ciphertext, [#(#nonce),*])))
+}
+
+#[cfg(not(feature = "encrypt"))]
+fn encrypt(_data: &LitStr, key_var: &LitStr) -> syn::Result<TokenStream> {
+    Err(syn::Error::new(
+        key_var.span(),
+        "The `encrypt` option requires the `encrypt` feature",
+    ))
+}
+
+/// Compresses `data` with the algorithm named by `format`
+fn compress(data: &LitStr, format: &LitStr) -> syn::Result<TokenStream> {
+    let bytes = data.value().into_bytes();
+    let (compressed, constructor) = match format.value().as_str() {
+        "gzip" => (gzip(&bytes, format)?, quote!(gzip)),
+        "zstd" => (zstd(&bytes, format)?, quote!(zstd)),
+        f => {
+            return Err(syn::Error::new(
+                format.span(),
+                format!("Unknown compression {}, expected gzip or zstd", f),
+            ))
+        }
+    };
+    let compressed = LitByteStr::new(&compressed, data.span());
+    Ok(quote!(::file_env_const_runtime::Compressed::#constructor(#compressed)))
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(bytes: &[u8], _format: &LitStr) -> syn::Result<Vec<u8>> {
+    Ok(file_env_const_runtime::gzip_compress(bytes))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip(_bytes: &[u8], format: &LitStr) -> syn::Result<Vec<u8>> {
+    Err(syn::Error::new(
+        format.span(),
+        "Compressing with gzip requires the `gzip` feature",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd(bytes: &[u8], _format: &LitStr) -> syn::Result<Vec<u8>> {
+    Ok(file_env_const_runtime::zstd_compress(bytes))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd(_bytes: &[u8], format: &LitStr) -> syn::Result<Vec<u8>> {
+    Err(syn::Error::new(
+        format.span(),
+        "Compressing with zstd requires the `zstd` feature",
+    ))
+}
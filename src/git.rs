@@ -0,0 +1,201 @@
+//! Reading metadata about the current commit from the git repository, for `git_env!`
+//!
+//! This reads `.git` directly rather than running `git`: `HEAD`, loose refs and `packed-refs`.
+//! That's enough for the commit hash, branch and an exact tag, but not for `git describe` or the
+//! dirty flag, which need the object database and index, so those fields are rejected. Every file
+//! is read through the cache, so with `nightly-tracking` moving `HEAD` or a ref rebuilds the crate.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+use syn::LitStr;
+
+use crate::{cache, Data, Kind};
+
+/// The number of hex digits in a short commit hash
+const SHORT_SHA_LEN: usize = 7;
+
+/// How many `ref:` links are followed when resolving a ref, as in git
+const MAX_SYMREF_DEPTH: usize = 5;
+
+/// The piece of metadata to embed, chosen by the `field` option
+#[derive(Clone, Copy)]
+pub(crate) enum Field {
+    Sha,
+    ShortSha,
+    Branch,
+    Tag,
+}
+
+impl Field {
+    /// Parses the `field` option, which is `"sha"` if not given
+    pub(crate) fn parse(field: Option<&LitStr>) -> syn::Result<Field> {
+        let Some(field) = field else {
+            return Ok(Field::Sha);
+        };
+        match field.value().as_str() {
+            "sha" => Ok(Field::Sha),
+            "short_sha" => Ok(Field::ShortSha),
+            "branch" => Ok(Field::Branch),
+            "tag" => Ok(Field::Tag),
+            "describe" | "dirty" => Err(syn::Error::new(
+                field.span(),
+                format!(
+                    "git_env! can't read the {} field, as it reads .git directly and that needs \
+                     the object database and index. Run git instead, with \
+                     file_env!(\"cmd:git describe --dirty\", ...) and the `cmd` feature, or give \
+                     an environment variable set from it in CI as the first argument",
+                    field.value()
+                ),
+            )),
+            f => Err(syn::Error::new(
+                field.span(),
+                format!(
+                    "Unknown git field {}, expected sha, short_sha, branch or tag",
+                    f
+                ),
+            )),
+        }
+    }
+}
+
+/// Reads `field` from the repository containing the crate being built
+pub(crate) fn read(field: Field) -> Kind {
+    let start = std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let span = Span::call_site();
+    let Some(repo) = Repo::find(&start) else {
+        return Kind::Name(start.to_string_lossy().to_string(), span);
+    };
+    let name = repo.git_dir.to_string_lossy().to_string();
+
+    let value = repo.head().and_then(|head| match field {
+        Field::Sha => repo.commit(&head),
+        Field::ShortSha => repo
+            .commit(&head)
+            .map(|sha| sha[..SHORT_SHA_LEN].to_string()),
+        Field::Branch => head.strip_prefix("refs/heads/").map(str::to_string),
+        Field::Tag => repo.commit(&head).and_then(|sha| repo.tag_of(&sha)),
+    });
+    match value {
+        Some(value) => Kind::Data(Data::text(&value, span), name),
+        None => Kind::Name(name, span),
+    }
+}
+
+struct Repo {
+    /// The repository's own git directory, holding `HEAD`
+    git_dir: PathBuf,
+    /// The directory holding refs shared between worktrees, usually the same as `git_dir`
+    common_dir: PathBuf,
+}
+
+impl Repo {
+    /// Finds the repository containing `start`, following `.git` files used by worktrees and
+    /// submodules
+    fn find(start: &Path) -> Option<Repo> {
+        let dot_git = start
+            .ancestors()
+            .map(|dir| dir.join(".git"))
+            .find(|d| d.exists())?;
+        let git_dir = if dot_git.is_file() {
+            let contents = read_text(&dot_git)?;
+            let target = contents.trim().strip_prefix("gitdir:")?.trim();
+            dot_git.parent()?.join(target)
+        } else {
+            dot_git
+        };
+        let common_dir = match read_text(&git_dir.join("commondir")) {
+            Some(common) => git_dir.join(common.trim()),
+            None => git_dir.clone(),
+        };
+        Some(Repo {
+            git_dir,
+            common_dir,
+        })
+    }
+
+    /// Returns `HEAD`, which is either a ref such as `refs/heads/main` or a commit hash
+    fn head(&self) -> Option<String> {
+        let head = read_text(&self.git_dir.join("HEAD"))?;
+        let head = head.trim();
+        Some(head.strip_prefix("ref:").unwrap_or(head).trim().to_string())
+    }
+
+    /// Resolves `reference`, a ref name or commit hash, to a commit hash
+    fn commit(&self, reference: &str) -> Option<String> {
+        self.follow(reference, MAX_SYMREF_DEPTH)
+    }
+
+    /// Resolves `reference` like [`Repo::commit`], following at most `depth` more `ref:` links, so
+    /// that a cycle of symbolic refs ends
+    fn follow(&self, reference: &str, depth: usize) -> Option<String> {
+        if is_sha(reference) {
+            return Some(reference.to_string());
+        }
+        for dir in [&self.git_dir, &self.common_dir] {
+            if let Some(target) = read_text(&dir.join(reference)) {
+                let target = target.trim();
+                return match target.strip_prefix("ref:") {
+                    Some(next) => self.follow(next.trim(), depth.checked_sub(1)?),
+                    None => is_sha(target).then(|| target.to_string()),
+                };
+            }
+        }
+        self.packed_refs()
+            .into_iter()
+            .find(|(name, _, _)| name == reference)
+            .map(|(_, sha, _)| sha)
+    }
+
+    /// Returns the name of a tag pointing at the commit `sha`
+    fn tag_of(&self, sha: &str) -> Option<String> {
+        let mut tags: Vec<String> = self
+            .packed_refs()
+            .into_iter()
+            .filter(|(_, target, peeled)| peeled.as_deref().unwrap_or(target) == sha)
+            .filter_map(|(name, _, _)| Some(name.strip_prefix("refs/tags/")?.to_string()))
+            .collect();
+        // Loose annotated tags point at a tag object, which can't be peeled without reading the
+        // object database, so only lightweight loose tags are found
+        let loose = self.common_dir.join("refs").join("tags");
+        for entry in std::fs::read_dir(loose).into_iter().flatten().flatten() {
+            let target = read_text(&entry.path()).unwrap_or_default();
+            if target.trim() == sha {
+                tags.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        tags.sort();
+        tags.into_iter().next()
+    }
+
+    /// Returns each ref in `packed-refs`, with its target and the commit it peels to, if any
+    fn packed_refs(&self) -> Vec<(String, String, Option<String>)> {
+        let packed = read_text(&self.common_dir.join("packed-refs")).unwrap_or_default();
+        let mut refs: Vec<(String, String, Option<String>)> = Vec::new();
+        for line in packed.lines() {
+            if let Some(peeled) = line.strip_prefix('^') {
+                if let Some(last) = refs.last_mut() {
+                    last.2 = Some(peeled.trim().to_string());
+                }
+            } else if let Some((sha, name)) = line.split_once(' ') {
+                if is_sha(sha) {
+                    refs.push((name.trim().to_string(), sha.to_string(), None));
+                }
+            }
+        }
+        refs
+    }
+}
+
+/// Reads the file at `path` in the repository through the cache, so that it's tracked
+fn read_text(path: &Path) -> Option<String> {
+    let contents = cache::read(path).ok()?;
+    String::from_utf8(contents.to_vec()).ok()
+}
+
+/// Returns true if `s` is a full SHA-1 or SHA-256 commit hash
+fn is_sha(s: &str) -> bool {
+    matches!(s.len(), 40 | 64) && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,49 @@
+//! Fetching values from `http://` and `https://` URLs, with the `http` feature
+//!
+//! A URL which can't be fetched, or any URL in offline mode, is treated like a missing file, so the
+//! next source is tried.
+
+use syn::LitStr;
+
+use crate::args::Options;
+use crate::Kind;
+
+/// How long to wait for a response when no `timeout` option is given
+#[cfg(feature = "http")]
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Fetches the URL `url`, unless offline
+#[cfg(feature = "http")]
+pub(crate) fn read(url: &LitStr, options: &Options) -> syn::Result<Kind> {
+    let timeout = match &options.timeout {
+        Some(timeout) => timeout.base10_parse()?,
+        None => DEFAULT_TIMEOUT_SECS,
+    };
+    if crate::offline() {
+        return Ok(Kind::Name(url.value(), url.span()));
+    }
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(std::time::Duration::from_secs(timeout)))
+        .build()
+        .into();
+    let body = agent
+        .get(url.value())
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_string());
+    match body {
+        Ok(body) => Ok(Kind::Data(
+            crate::Data::text(&body, url.span()),
+            url.value(),
+        )),
+        Err(_) => Ok(Kind::Name(url.value(), url.span())),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+pub(crate) fn read(url: &LitStr, _options: &Options) -> syn::Result<Kind> {
+    Err(syn::Error::new(
+        url.span(),
+        "Fetching a URL requires the `http` feature",
+    ))
+}
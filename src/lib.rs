@@ -42,19 +42,875 @@
 //!     env_file!( "ENV_NOT_FOUND", "file_does_not_exist", "fallback string");
 //! assert_eq!(FALL_BACK_TO_DEFAULT, "fallback string");
 //! ```
+//!
+//! ## Defaults which aren't string literals
+//! The default can also be an expression, such as `concat!(...)`, `include_str!(...)` or a
+//! constant, so defaults can be shared rather than repeated. A constant's name must have an upper
+//! case letter, or a path such as `crate::defaults::host`, to tell it apart from an option. As its
+//! value is only known when the expansion is compiled, options which check or change the value
+//! don't apply to it
+//! ```
+//!# use file_env_const::file_env;
+//! const DEFAULT_HOST: &'static str = "localhost";
+//!
+//! const HOST: &'static str = file_env!("file_does_not_exist", "ENV_NOT_FOUND", DEFAULT_HOST);
+//! assert_eq!(HOST, "localhost");
+//!
+//! const URL: &'static str =
+//!     file_env!("file_does_not_exist", "ENV_NOT_FOUND", concat!("https://", "example.com"));
+//! assert_eq!(URL, "https://example.com");
+//! ```
+//!
+//! ## Finding out which source was used
+//! The `_with_source` variants additionally return a `Source` from the companion
+//! [`file_env_const_runtime`](https://docs.rs/file_env_const_runtime) crate, which must be added
+//! as a dependency
+//! ```
+//!# use file_env_const::file_env_with_source;
+//! use file_env_const_runtime::Source;
+//!
+//! const DATA: (&'static str, Source) =
+//!     file_env_with_source!("file_does_not_exist", "ENV_NOT_FOUND", "fallback string");
+//! assert_eq!(DATA, ("fallback string", Source::Default));
+//! ```
+//!
+//! ## Describing a value
+//! `#[metadata]` on a constant initialized by `file_env!`, `env_file!` or their bytes variants also
+//! defines `<NAME>_LEN`, `<NAME>_LINES` and `<NAME>_IS_DEFAULT`, computed at compile time, so
+//! buffers can be sized and a build using the default can be detected
+//! ```
+//!# use file_env_const::{file_env, metadata};
+//! #[metadata]
+//! const MOTD: &str = file_env!("file_does_not_exist", "ENV_NOT_FOUND", "Hello\nWorld\n");
+//! assert_eq!((MOTD_LEN, MOTD_LINES, MOTD_IS_DEFAULT), (12, 2, true));
+//! ```
+//!
+//! ## Splitting a value
+//! `file_env_split!` splits a value by a pattern into several constants, named by the upper case
+//! identifiers in the pattern, and fails to compile if the value doesn't have its shape
+//! ```
+//!# use file_env_const::file_env_split;
+//! file_env_split!("HOST:PORT" <- "addr.txt", "ADDR", "localhost:8080");
+//! assert_eq!((HOST, PORT), ("localhost", "8080"));
+//! ```
+//!
+//! ## Defining several constants
+//! `file_env_consts!` defines a constant for each item in a block, each with its own chain of
+//! sources tried in order and an optional default, so a crate's build time configuration can be
+//! read in one place
+//! ```
+//!# use file_env_const::file_env_consts;
+//! file_env_consts! {
+//!     API_URL: env "API_URL" | file "api_url.txt" | "https://localhost";
+//!     BUILD_CHANNEL: env "CHANNEL" | "dev";
+//! }
+//! ```
+//!
+//! ## Embedding a hash
+//! `file_env_hash!` takes the same arguments as `file_env!`, but embeds the hex SHA-256 digest of
+//! the value, which is useful for cache-busting asset URLs
+//! ```
+//!# use file_env_const::file_env_hash;
+//! const CARGO_HASH: &'static str = file_env_hash!("Cargo.toml", "CARGO_PKG_NAME");
+//! assert_eq!(CARGO_HASH.len(), 64);
+//! ```
+//!
+//! ## Embedding git metadata
+//! `git_env!` reads an environment variable, falling back to the current commit's hash, branch or
+//! tag read from `.git`, and then a default
+//! ```
+//!# use file_env_const::git_env;
+//! const COMMIT: &'static str = git_env!("GIT_SHA", "unknown");
+//! ```
+//!
+//! ## Embedding the build time
+//! `build_time_env!` embeds an RFC 3339 timestamp, from `SOURCE_DATE_EPOCH` when it's set so that
+//! reproducible builds get the same value, then an environment variable, then the current time
+//! ```
+//!# use file_env_const::build_time_env;
+//! const BUILT: &'static str = build_time_env!("BUILD_TIME", format = "%Y-%m-%d %H:%M");
+//! ```
+//!
+//! ## Embedding file metadata
+//! `file_meta_env!` embeds a file's modification time, or its size with `field = "size"`, as a
+//! `u64`, falling back to an environment variable and a default, so a large asset can be
+//! fingerprinted without hashing it
+//! ```
+//!# use file_env_const::file_meta_env;
+//! const WASM_MTIME: u64 = file_meta_env!("assets/app.wasm", "APP_WASM_MTIME", "0");
+//! const WASM_SIZE: u64 = file_meta_env!("assets/app.wasm", "APP_WASM_SIZE", "0", field = "size");
+//! ```
+//!
+//! ## Embedding bytes
+//! `file_env_bytes!` and `env_file_bytes!` take the same arguments as `file_env!` and
+//! `env_file!`, but embed a `&'static [u8]`, so files don't need to be UTF-8. Their default can
+//! be a byte string, and on Unix an environment variable which isn't Unicode is embedded as its
+//! raw bytes
+//! ```
+//!# use file_env_const::file_env_bytes;
+//! const HEADER: &[u8] = file_env_bytes!("Cargo.toml", "HEADER", length = 11);
+//! assert_eq!(HEADER, b"[workspace]");
+//! ```
+//!
+//! ## Joining several sources
+//! `concat_env_file!` resolves several groups of `env_file!` arguments and joins the results, so
+//! a certificate chain can be built from files which can each be overridden by an environment
+//! variable
+//! ```
+//!# use file_env_const::concat_env_file;
+//! const CHAIN: &'static str = concat_env_file!(
+//!     ("CA_PEM", "ca.pem", ""),
+//!     ("INTERMEDIATE_PEM", "intermediate.pem", ""),
+//!     separator = "\n"
+//! );
+//! ```
+//!
+//! ## Including code
+//! `file_env_include!` splices the value into the program as Rust tokens, like `include!`, with a
+//! braced block of code as the default
+//! ```
+//!# use file_env_const::file_env_include;
+//! file_env_include!("generated/handlers.rs", "HANDLERS_RS", {
+//!     fn handlers() -> &'static [&'static str] {
+//!         &[]
+//!     }
+//! });
+//! ```
+//!
+//! ## Checking whether a source exists
+//! `file_env_exists!` expands to a `bool`, for code which only enables a feature when an optional
+//! file or secret was present at build time
+//! ```
+//!# use file_env_const::file_env_exists;
+//! const HAS_LICENSE: bool = file_env_exists!("license.key", "LICENSE_KEY");
+//! ```
+//!
+//! ## Collecting variables by prefix
+//! `env_prefix_map!` embeds every build time environment variable starting with a prefix as a
+//! sorted slice of names and values, with an optional `.env` style file of defaults for variables
+//! which aren't set, such as a snapshot of feature flags
+//! ```ignore
+//!# use file_env_const::env_prefix_map;
+//! const FLAGS: &[(&str, &str)] = env_prefix_map!("MYAPP_FLAG_", "flags.env");
+//! ```
+//!
+//! ## Generating a struct from TOML
+//! With the `toml` feature, `toml_const!` fills a constant struct from a TOML file at compile time,
+//! with each field optionally overridden by an environment variable, replacing a build script which
+//! generates the same code. Without a struct definition, one is inferred from the top-level keys
+//! ```ignore
+//!# use file_env_const::toml_const;
+//! toml_const! {
+//!     pub const CONFIG: Config = "config.toml";
+//!
+//!     pub struct Config {
+//!         pub name: &'static str,
+//!         #[env = "APP_PORT"]
+//!         pub port: u16,
+//!         #[key = "server.timeout"]
+//!         pub timeout: f64,
+//!     }
+//! }
+//! ```
+//!
+//! ## Choosing sources per profile or target
+//! Instead of string arguments, each branch gives the arguments for builds with debug assertions
+//! (`debug`), without them (`release`), or matching a `cfg` predicate, such as the target. The
+//! first branch which applies is used, then the `default` string
+//! ```
+//!# use file_env_const::file_env;
+//! const CONFIG: &str = file_env!(
+//!     cfg(windows) = ("config/windows.toml", "WINDOWS_CONFIG"),
+//!     debug = ("config/dev.toml", "DEV_CONFIG"),
+//!     release = ("config/prod.toml", "PROD_CONFIG"),
+//!     default = ""
+//! );
+//! ```
+//! Every branch is resolved, as the choice between them is made by `cfg!` in the expansion, so
+//! strict mode and `required` apply to branches for other profiles too.
+//!
+//! A `feature("name")` branch applies when the crate using the macro is built with that cargo
+//! feature, so one constant can follow the feature matrix
+//! ```
+//!# use file_env_const::file_env;
+//! const TELEMETRY_KEY: &str = file_env!(
+//!     feature("telemetry") = ("telemetry.key", "TELEMETRY_KEY"),
+//!     default = ""
+//! );
+//! ```
+//!
+//! # Source prefixes
+//! Each string argument other than the default is normally read according to its position, but a
+//! `file:` or `env:` prefix reads it as a file or environment variable wherever it appears. With
+//! the `http` feature an argument starting with `https://` or `http://` is fetched at build time,
+//! so CI can pull a value from an internal endpoint while offline builds fall back to a local file.
+//! A URL which can't be fetched within the `timeout` is treated as not found, and URLs are skipped
+//! entirely in [offline mode](#offline-mode).
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const NAME: &'static str = file_env!("env:CARGO_PKG_NAME", "Cargo.toml");
+//! assert_eq!(NAME, "file_env_const");
+//! ```
+//!
+//! ```ignore
+//!# use file_env_const::file_env;
+//! const FLAGS: &'static str = file_env!(
+//!     "https://config.internal/flags.json",
+//!     "flags.json",
+//!     "{}",
+//!     timeout = 5
+//! );
+//! ```
+//!
+//! With the `cmd` feature an argument starting with `cmd:` is run as a command, and its standard
+//! output, without a trailing newline, is the value. The command is split on whitespace and run
+//! without a shell from the crate's directory, and is treated as not found if it fails.
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const DISTRO: &'static str = file_env!("/etc/os-release", "DISTRO", "unknown", key = "ID");
+//! ```
+//!
+//! ```ignore
+//!# use file_env_const::file_env;
+//! const VERSION: &'static str = file_env!("cmd:git describe --tags", "VERSION", "unknown");
+//! ```
+//!
+//! A `provider:` source, also with the `cmd` feature, fetches a secret from Vault, a cloud secret
+//! manager or a password manager by running the command template in `FILE_ENV_CONST_PROVIDER`,
+//! or the `provider` in `file_env_const.toml` if that isn't set, so the template can be committed
+//! with the crate. For `provider:ci/api_token`, `{path}` in the template is replaced by `ci`, `{key}`
+//! by `api_token`, and `{name}` by the whole of `ci/api_token`.
+//!
+//! ```ignore
+//!# use file_env_const::env_file;
+//! // Built with FILE_ENV_CONST_PROVIDER="vault kv get -field={key} {path}"
+//! const TOKEN: &'static str = env_file!("API_TOKEN", "provider:ci/api_token", "", secret);
+//! ```
+//!
+//! # Filenames
+//! Relative filenames are resolved from the root of the crate being built, or the `base_dir` in
+//! `file_env_const.toml`. Either `/` or `\` can be used as the separator on every platform, and `.`
+//! and `..` are resolved without following symlinks, so the same invocation works on Windows and
+//! Unix build agents. Absolute paths, including drive letters, UNC paths and extended-length `\\?\`
+//! paths on Windows, are used as they are. With the `search_up` option, a relative filename which
+//! isn't found is looked for in each parent directory, up to the nearest one whose `Cargo.toml` has
+//! a `[workspace]` table.
+//!
+//! # Audit manifest
+//! Setting `FILE_ENV_CONST_MANIFEST` to a path at build time makes every macro invocation append a
+//! JSON record to that file, one per line, with the macro name, call site, the kind of source which
+//! was used (`file`, `env`, `http`, `cmd`, `provider`, `git`, `clock` or `default`), the path or
+//! variable name, the SHA-256 of the embedded content, whether the source is
+//! [hermetic](#reproducible-builds), and every source which was looked up as `<kind>:<name>`.
+//! The SHA-256 is `null` for `secret` values:
+//! ```json
+//! {"macro":"file_env","call_site":"src/main.rs:2:17","source":"file","name":"/path/to/Cargo.toml","sha256":"effa43...","hermetic":true,"looked_up":["file:/path/to/Cargo.toml"]}
+//! ```
+//!
+//! # Options
+//! Options can be given after the string arguments of any macro, either as flags or as
+//! `name = value` pairs
+//!
+//! * `required`: fail the build if the first source isn't found, even if later sources would be
+//! * `quiet`: never print fallback notices for this invocation
+//! * `secret`: replace the path or variable name with a hash in any notices, errors or manifest
+//!   records, so that build logs don't reveal which secrets are used, and leave the value's
+//!   SHA-256 out of the manifest. The value itself is never printed, whether or not this is set.
+//!   On Unix a secret file which every user can read, or which is owned by another user, prints a
+//!   warning, or is a compile error with `secret_permissions = "error"` in `file_env_const.toml`
+//! * `allow_sensitive`: allow embedding a file which looks like a private key. Without this, files
+//!   with the names `ssh-keygen` uses for private keys, anything in an `.ssh` directory other than
+//!   public keys, and files containing PEM or PuTTY private keys are a compile error, whatever
+//!   options like `trim` or `line` select from them
+//! * `obfuscate`: embed the value as a `file_env_const_runtime::Obfuscated`, which stores the bytes
+//!   XORed with a keystream and reconstructs the string on first use. This keeps the value out of
+//!   the output of `strings`, but isn't encryption
+//! * `encrypt = "KEY_VAR"`: with the `encrypt` feature, encrypt the value with ChaCha20-Poly1305
+//!   using the key of 64 hex digits in the build time environment variable `KEY_VAR`. The value is
+//!   embedded as a `file_env_const_runtime::Encrypted` (which needs the `encrypt` feature of that
+//!   crate too), and can only be read at runtime by calling `decrypt` with the same key
+//! * `compress = "gzip"` or `compress = "zstd"`: with the feature of the same name, compress the
+//!   value, embedding it as a `file_env_const_runtime::Compressed` which is decompressed on first
+//!   use. The runtime crate needs the same feature enabled
+//! * `intern`: store the value in a single hidden static, and expand to a `&'static str` pointing
+//!   at it, or a `&'static [u8]` with the bytes macros. A literal in a constant is otherwise copied
+//!   into every codegen unit which uses the constant, so large files used from several modules can
+//!   be duplicated in the binary. Every invocation in the crate interning the same content shares
+//!   one static, whether it's a string or bytes. This needs Rust 1.82, and using the value in a
+//!   `const` needs Rust 1.83
+//! * `expect_sha256 = "..."`: fail the build unless the hex SHA-256 digest of the value is the one
+//!   given, so that a stale or tampered file can't be embedded
+//! * `minisign_key = "RW..."`: with the `signature` feature, fail the build unless the value has a
+//!   valid minisign signature from this public key. The signature is read from a `.sig` file next
+//!   to the file the value came from, or from the environment variable named by
+//!   `signature_env = "VAR"`. A file's signature covers the whole file, before options like `trim`
+//!   or `line`
+//! * `timeout = N`: the number of seconds to wait when fetching a URL, 10 by default
+//! * `line = N` or `lines = A..B`: embed only line `N`, or the lines in the range, counting from 1.
+//!   The range can be written `A..B`, `A..=B` or `A..`, like a Rust range. If the value doesn't
+//!   have those lines, the next source is tried
+//! * `key = "NAME"`: embed only the value of the first `NAME=value` line, as in `/etc/os-release`
+//!   or `.env` files. Lines starting with `#` are ignored, as is an `export` before the name, and
+//!   quotes around the value are removed. If there's no such line, the next source is tried. As
+//!   with dotenvy, `${OTHER}` and `$OTHER` in a value which isn't single quoted are replaced by the
+//!   build time environment variable `OTHER`, or the value of the key `OTHER` earlier in the file,
+//!   and `\$` is a literal `$`
+//! * `extract = r"..."`: with the `regex` feature, embed only part of the value matched by this
+//!   regular expression: the first named capture group, the first group if none are named, or the
+//!   whole match if there are no groups. If the value doesn't match, the next source is tried
+//! * `strip_comments = "#"`: remove blank lines, and lines which start with the comment marker
+//!   after any indentation, so that commentary in config files and word lists isn't embedded
+//! * `offset = N` and `length = N`: embed only `length` bytes starting at byte `offset`, either of
+//!   which can be left out. With the string macros these must select whole characters. If the
+//!   value is too short, the next source is tried
+//! * `chars = A..B`: embed only the characters in the range, counting from 0. If the value is too
+//!   short, the next source is tried
+//! * `template`: replace `${VAR}` and `${VAR:-default}` placeholders in the value with build time
+//!   environment variables, failing the build if a variable without a default isn't set. `$${`
+//!   is a literal `${`. Like every option which changes the value, this applies to whichever
+//!   source is found, but not to the default
+//! * `transform = "lowercase"` or `transform = "uppercase"`: change the case of the value, such as
+//!   a host name from an environment variable whose casing isn't under your control
+//! * `minify = "json"` or `minify = "whitespace"`: compact the value, after any other
+//!   transformations. `json` fails the build unless the value is valid JSON, and removes the
+//!   whitespace between tokens. `whitespace` trims the value and replaces each run of whitespace
+//!   with a single newline, if it contains one, or a single space
+//! * `normalize = "nfc"`: with the `normalize` feature, convert the value to a Unicode
+//!   normalization form, one of `nfc`, `nfd`, `nfkc` or `nfkd`, so that files saved by different
+//!   editors embed the same bytes
+//! * `merge`: merge the overlay beside the file into it, such as `config.local.toml` for
+//!   `config.toml`, if the overlay exists. Tables are merged recursively and other values in the
+//!   overlay replace the file's. The merged document is written out again, so comments and
+//!   formatting aren't kept. JSON files are always supported, and TOML files need the `toml`
+//!   feature
+//! * `overlay = "config.${APP_ENV:-dev}.toml"`: merge this overlay, in the same directory as the
+//!   file, instead. `${VAR}` placeholders are replaced as with `template`
+//! * `trim`: remove leading and trailing whitespace from the value, such as the newline at the end
+//!   of a secret file
+//! * `max_size = N`: fail the build if the value is larger than `N` bytes, so an unexpectedly large
+//!   file isn't embedded by mistake
+//! * `env_prefix = "MYAPP_"`: prepend the prefix to the name of every environment variable source,
+//!   so a library crate can namespace its build time configuration. `env_prefix = ""` turns off a
+//!   prefix set in `file_env_const.toml`
+//! * `label = "name"`: the name used for the value's `FILE_ENV_CONST_OVERRIDE_<NAME>` override,
+//!   instead of its environment variable
+//! * `follow_symlinks = false`: fail the build if the file is outside the crate root, or is reached
+//!   through a symlink below it, so an embedded file can't come from outside the repository. The
+//!   root can be changed with `root` in `file_env_const.toml`
+//! * `unescape_newlines`: replace `\n` and `\r\n` escapes in an environment variable with newlines,
+//!   for CI systems which can only set single-line variables, such as a PEM certificate.
+//!   `unescape_tabs` does the same for `\t`, and with either `\\` is an escaped backslash
+//! * `schema = "schemas/config.schema.json"`: with the `schema` feature, fail the build unless the
+//!   value is JSON matching the JSON Schema in this file, which is found like a filename. The error
+//!   gives the JSON path of the first mismatch, such as `$.server.port`. Keywords the validator
+//!   doesn't support, such as `$ref` and `pattern`, are an error
+//! * `deserialize = "json"` or `deserialize = "toml"`: fail the build unless the value is valid
+//!   JSON or TOML, and embed it as a `std::sync::LazyLock<T>` which deserializes it with serde on
+//!   first use, for types such as maps and owned strings which can't be constants. This needs the
+//!   `json` or `toml` feature of the runtime crate, and checking TOML needs the `toml` feature of
+//!   this one. The shape of `T` is only checked at runtime, as the macro can't see the type
+//! * `validate = "rfc3339"`: fail the build unless the value is an RFC 3339 timestamp, such as
+//!   `2030-01-01T00:00:00Z`, so expiry dates and cut-offs baked into the binary are well-formed
+//! * `epoch`: embed an RFC 3339 timestamp as a `u64` number of seconds since the Unix epoch,
+//!   failing the build if it isn't one or is before 1970
+//! * `validate = "url"`: fail the build unless the value is an absolute URL, so a mistyped endpoint
+//!   in an environment variable fails CI rather than every connection at runtime. The scheme must
+//!   be `https` or `http`, or one of the comma-separated list in `schemes = "https,wss"`
+//! * `message = "text"`: shown in the compile error when the value can't be resolved, such as
+//!   `message = "Set PROD_SIGNING_KEY or provide keys/prod.pem"`. It replaces the usual error when
+//!   every source fails and there's no default, and follows the reason when strict mode or
+//!   `required` fails the build
+//! * `section = ".app_config"`: for the bytes macros, store the bytes in a static placed in this
+//!   linker section with `#[link_section]` and `#[used]`, such as external flash on an embedded
+//!   target, and expand to a `&'static [u8]` pointing to it. Using the value in a `const` needs Rust
+//!   1.83, so it's usually assigned to a `static`
+//! * `align = 16`: for the bytes macros, store the bytes in a static aligned to this many bytes, a
+//!   power of two, so the data can be cast to a zero-copy structure without copying it first
+//! * `verify_match`: also read the sources after the one which was found, and fail the build if
+//!   any of them exists with a different value, showing the first line which differs unless the
+//!   value is `secret`. This catches an environment variable set in CI drifting from the file
+//!   checked into the repository
+//! * `on_invalid = "fallback"`: treat a source whose value fails `validate`, `schema` or
+//!   `deserialize`, or can't be transformed by `minify` or `template`, like a missing one, so a
+//!   corrupt local file falls back to the environment variable or default. `on_invalid = "error"`,
+//!   the default, fails the build instead. The default itself is still checked. A file or
+//!   environment variable which isn't UTF-8 is always treated as missing by the text macros,
+//!   whichever is chosen
+//! * `search_up`: look for a relative filename in each parent directory too, up to the workspace
+//!   root, using the nearest which exists, like `.env` discovery, so the members of a workspace can
+//!   share one `secrets/` directory without `../../` paths
+//! * `dev_placeholder = "..."`: in builds with debug assertions, such as the dev profile, embed this
+//!   instead of the value, for a large asset which debug builds load from disk anyway. The macro
+//!   expands to the placeholder and to an invocation of itself without it, each behind `#[cfg]`, so
+//!   debug builds strip the invocation before it's expanded and never read, check or embed the
+//!   sources. The invocation names the crate as `::file_env_const`, so it can't be renamed
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const MANIFEST: &'static str = file_env!("Cargo.toml", "CARGO_PKG_NAME", required);
+//! ```
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! use file_env_const_runtime::Obfuscated;
+//!
+//! static NAME: Obfuscated = file_env!("no_such_file", "CARGO_PKG_NAME", obfuscate);
+//! assert_eq!(&*NAME, "file_env_const");
+//! ```
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const LICENSE: &'static str = file_env!("LICENSE", "LICENSE_TEXT", intern);
+//! assert!(LICENSE.starts_with("The MIT License"));
+//! ```
+//!
+//! ```ignore
+//!# use file_env_const::file_env;
+//! use std::collections::HashMap;
+//! use std::sync::LazyLock;
+//!
+//! static ROUTES: LazyLock<HashMap<String, String>> =
+//!     file_env!("routes.json", "ROUTES", "{}", deserialize = "json");
+//! ```
+//!
+//! ```ignore
+//!# use file_env_const::file_env;
+//! use file_env_const_runtime::Encrypted;
+//!
+//! // Built with CONFIG_KEY set to the deployment key
+//! static CONFIG: Encrypted = file_env!("config.toml", "CONFIG", encrypt = "CONFIG_KEY");
+//!
+//! let config = CONFIG.decrypt_hex(&std::env::var("CONFIG_KEY")?)?;
+//! ```
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const CHECKED: &'static str = file_env!(
+//!     "no_such_file",
+//!     "ENV_NOT_FOUND",
+//!     "abc",
+//!     expect_sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+//! );
+//! ```
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const TITLE: &'static str = file_env!("CHANGELOG.md", "TITLE", line = 1);
+//! assert_eq!(TITLE, "# CHANGELOG");
+//!
+//! const MISSING: &'static str = file_env!("CHANGELOG.md", "ENV_NOT_FOUND", "none", lines = 1000..);
+//! assert_eq!(MISSING, "none");
+//! ```
+//!
+//! ```ignore
+//!# use file_env_const::file_env;
+//! const VERSION: &'static str = file_env!(
+//!     "Cargo.toml",
+//!     "VERSION",
+//!     extract = r#"version = "(?P<v>[^"]+)""#
+//! );
+//! ```
+//!
+//! ```
+//!# use file_env_const::env_file;
+//! const NAME: &'static str = env_file!("CARGO_PKG_NAME", "Cargo.toml", transform = "uppercase");
+//! assert_eq!(NAME, "FILE_ENV_CONST");
+//! ```
+//!
+//! ```ignore
+//!# use file_env_const::file_env;
+//! // config.toml contains `port = ${PORT:-8080}`
+//! const CONFIG: &'static str = file_env!("config.toml", "CONFIG", template);
+//! ```
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const EXPIRES: u64 = file_env!("expiry", "EXPIRES_AT", "2030-01-01T00:00:00Z", epoch);
+//! assert_eq!(EXPIRES, 1893456000);
+//! ```
+//!
+//! ```
+//!# use file_env_const::file_env;
+//! const HOMEPAGE: &'static str = file_env!(
+//!     "Cargo.toml",
+//!     "ENV_NOT_FOUND",
+//!     "https://example.com",
+//!     validate = "url",
+//!     on_invalid = "fallback"
+//! );
+//! assert_eq!(HOMEPAGE, "https://example.com");
+//! ```
+//!
+//! ```compile_fail
+//!# use file_env_const::file_env;
+//! const CHECKED: &'static str =
+//!     file_env!("no_such_file", "ENV_NOT_FOUND", "abc", expect_sha256 = "0123");
+//! ```
+//!
+//! ```compile_fail
+//!# use file_env_const::file_env;
+//! use std::collections::HashMap;
+//! use std::sync::LazyLock;
+//!
+//! static NAMES: LazyLock<HashMap<String, String>> =
+//!     file_env!("no_such_file", "ENV_NOT_FOUND", r#"{"name": "\é"}"#, deserialize = "json");
+//! ```
+//!
+//! ```compile_fail
+//!# use file_env_const::file_env;
+//! const CERT: &'static str = file_env!("prod-cert.pem", "PROD_CERT", "fallback", required);
+//! ```
+//!
+//! An argument missing, one too many, or a filename and environment variable name given the wrong
+//! way round is reported at the argument, with the arguments the macro expects
+//! ```compile_fail
+//!# use file_env_const::file_env;
+//! const CONFIG: &'static str = file_env!("APP_CONFIG", "config/app.toml", "fallback");
+//! ```
+//!
+//! # Large files
+//! Values over 1 MiB are expanded to an `include_str!` rather than a string literal, as rustc is
+//! much faster at handling these. In a crate with a build script the value is written to a file
+//! under `OUT_DIR` named after its hash and included from there, and otherwise a value which was
+//! read from a file is included directly from that file.
+//!
+//! A large asset still makes every binary bigger. The `dev_placeholder` option leaves it out of
+//! builds with debug assertions, embedding the placeholder instead, which is useful when debug
+//! builds load the asset from disk anyway. As the asset isn't read in these builds, nothing is
+//! written under `OUT_DIR` for it either
+//! ```
+//!# use file_env_const::file_env;
+//! const LICENSE: &str = file_env!("LICENSE", "LICENSE_TEXT", dev_placeholder = "MIT");
+//! assert_eq!(LICENSE == "MIT", cfg!(debug_assertions));
+//! ```
+//!
+//! # Logging
+//! Fallback notices are printed to stderr during compilation according to `FILE_ENV_CONST_LOG`:
+//! `off` prints nothing, `warn` only prints when falling back to the default, and `info` prints
+//! every fallback. When it isn't set notices are off, unless the `log` feature is enabled.
+//!
+//! Setting `FILE_ENV_CONST_LOG_FILE` to a path appends a line to that file for every invocation,
+//! whatever the level, with the crate, the call site, each source looked up, the one used or the
+//! error, and how long it took. This makes it easy to see why a build picked up a value.
+//! ```text
+//! app src/main.rs:4:25 file_env!: No file found at /app/config.toml; found the environment variable
+//! APP_KEY; used the environment variable APP_KEY in 0.041ms
+//! ```
+//! Each entry is written on a single line, wrapped here for readability. Secret names are redacted.
+//!
+//! # Strict mode
+//! Enabling the `strict` feature, or setting `FILE_ENV_CONST_STRICT=1` at build time, turns every
+//! fallback from one source to the next into a compile error. This is useful in release builds,
+//! where a missing file or environment variable is more likely to be a mistake than a convenience.
+//!
+//! # Reproducible builds
+//! Setting `FILE_ENV_CONST_REPRODUCIBLE=warn` at build time prints a warning for every value taken
+//! from a source which can differ between two builds of the same commit: an environment variable, a
+//! URL, a command or the current time. `FILE_ENV_CONST_REPRODUCIBLE=error` makes these compile
+//! errors instead, and the audit manifest's `hermetic` field gives a report of all of them. Files
+//! in the workspace, git metadata, `SOURCE_DATE_EPOCH` and defaults are treated as hermetic, but a
+//! file outside the workspace, such as `/etc/os-release`, or with an overlay merged into it isn't.
+//!
+//! # Offline mode
+//! Setting `FILE_ENV_CONST_OFFLINE=1` at build time skips every file, environment variable, URL,
+//! command and git source, and uses the defaults, for hermetic build systems which restrict access
+//! to them. This is also enabled on docs.rs. A value without a default is a compile error, and
+//! `file_env_exists!` is always `false`. Strict mode and `required` don't apply, as nothing is
+//! looked up to fall back from, but overrides and `SOURCE_DATE_EPOCH` are still used.
+//!
+//! # Overrides
+//! Setting `FILE_ENV_CONST_OVERRIDE_<NAME>` at build time forces a value, winning over every
+//! source, so release engineering can set values across a whole workspace build without touching
+//! call sites. `NAME` is the value's environment variable, after any `env_prefix`, or its `label`
+//! option, in upper case with anything other than letters and digits replaced by `_`. The override
+//! is embedded exactly as it's set, without any of the options which change the value.
+//!
+//! # Rebuilding when a value changes
+//! Cargo only expands the macros again when the crate itself changes, so an incremental build can
+//! keep embedding a value after its file or environment variable has changed. On nightly, the
+//! `nightly-tracking` feature reads them through `proc_macro::tracked`, so the compiler rebuilds
+//! the crate when any environment variable the macros looked up, or any file they read, changes.
+//! Files which didn't exist aren't tracked, so creating one of them still needs the crate to be
+//! touched. On stable, the companion `file_env_const_build` crate tells cargo the same from the
+//! crate's build script, either from a list of files and environment variables, or from the audit
+//! manifest written by the previous build.
+//!
+//! # Configuration file
+//! An optional `file_env_const.toml` in the root of the crate being built sets defaults for every
+//! invocation in the crate. It's a flat list of `key = value` lines, and each setting is optional
+//! ```text
+//! # Resolve relative filenames from config/ rather than the crate root
+//! base_dir = "config"
+//! # The same as the `strict` feature
+//! strict = true
+//! # The same as giving every invocation the `trim` and `max_size = 65536` options
+//! trim = true
+//! max_size = 65536
+//! # Look up MYAPP_API_KEY for env_file!("API_KEY", ...)
+//! env_prefix = "MYAPP_"
+//! # Forbid symlinks and files outside the parent directory, such as a workspace root
+//! follow_symlinks = false
+//! root = ".."
+//! # Fail the build for secret files other users can read, rather than warning
+//! secret_permissions = "error"
+//! # The command template for `provider:` sources, unless FILE_ENV_CONST_PROVIDER is set
+//! provider = "vault kv get -field={key} {path}"
+//! ```
+
+#![cfg_attr(
+    feature = "nightly-tracking",
+    feature(proc_macro_tracked_env, proc_macro_tracked_path)
+)]
+
+mod args;
+mod cache;
+mod cmd;
+mod config;
+mod consts;
+mod embed;
+mod git;
+mod http;
+mod json;
+mod log;
+mod manifest;
+mod merge;
+mod metadata;
+mod path;
+mod permissions;
+mod reproducible;
+mod schema;
+mod sensitive;
+mod sha256;
+mod signature;
+mod split;
+mod time;
+mod toml_const;
+mod track;
+mod transform;
+mod validate;
 
 use std::path::PathBuf;
+use std::time::Instant;
 
 use proc_macro::TokenStream;
-use quote::ToTokens;
-use syn::parse::Parser;
-use syn::punctuated::Punctuated;
-use syn::LitStr;
-use syn::Token;
+use proc_macro2::Span;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Ident, LitByteStr, LitStr};
+
+use crate::args::{Args, ConcatArgs, Options};
+
+/// A value which has been found, along with the span of the argument it was found from
+///
+/// This is always UTF-8, except when read from a file by the bytes macros.
+struct Data {
+    bytes: Vec<u8>,
+    span: Span,
+}
+
+impl Data {
+    fn text(value: &str, span: Span) -> Data {
+        Data {
+            bytes: value.as_bytes().to_vec(),
+            span,
+        }
+    }
+
+    fn from_lit(lit: &LitStr) -> Data {
+        Data::text(&lit.value(), lit.span())
+    }
+
+    /// Returns the value as a string, or an error if it isn't UTF-8
+    fn as_str(&self) -> syn::Result<&str> {
+        std::str::from_utf8(&self.bytes)
+            .map_err(|_| syn::Error::new(self.span, "The value isn't valid UTF-8"))
+    }
+}
 
 enum Kind {
-    Data(LitStr),
-    Name(String),
+    /// The value was found, along with the path or variable name it was found at
+    Data(Data, String),
+    /// The value wasn't found at the path or variable name given by the argument at this span
+    Name(String, Span),
+}
+
+/// A place a value can be loaded from
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    File,
+    Env,
+    Http,
+    Cmd,
+    /// A secret fetched by the command template in `FILE_ENV_CONST_PROVIDER`
+    Provider,
+    /// The current commit of the git repository, which doesn't take an argument
+    Git,
+    /// `SOURCE_DATE_EPOCH`, formatted as a timestamp, which doesn't take an argument
+    Epoch,
+    /// The current time, which doesn't take an argument
+    Clock,
+    Default,
+}
+
+impl Source {
+    /// Returns the kind of source `arg` names when given in this position
+    ///
+    /// A `file:`, `env:`, `cmd:` or `provider:` prefix, or a URL, overrides the kind of source for
+    /// the position.
+    fn for_arg(self, arg: &LitStr) -> Source {
+        let arg = arg.value();
+        if arg.starts_with("https://") || arg.starts_with("http://") {
+            Source::Http
+        } else if arg.starts_with("file:") {
+            Source::File
+        } else if arg.starts_with("env:") {
+            Source::Env
+        } else if arg.starts_with("cmd:") {
+            Source::Cmd
+        } else if arg.starts_with("provider:") {
+            Source::Provider
+        } else {
+            self
+        }
+    }
+
+    /// Returns true if `arg` is labelled with the kind of source it names, such as `env:`, or is
+    /// a URL
+    fn is_labelled(arg: &LitStr) -> bool {
+        Source::Default.for_arg(arg) != Source::Default
+    }
+
+    /// Returns true if this source is named by one of the macro's string arguments
+    fn takes_argument(self) -> bool {
+        !matches!(
+            self,
+            Source::Git | Source::Epoch | Source::Clock | Source::Default
+        )
+    }
+
+    /// Returns true if falling back from this source is expected, and so is never an error or
+    /// logged
+    fn is_optional(self) -> bool {
+        matches!(self, Source::Epoch)
+    }
+
+    /// Returns true if a value from this source is the same in every build of the same commit
+    fn is_hermetic(self) -> bool {
+        matches!(
+            self,
+            Source::File | Source::Git | Source::Epoch | Source::Default
+        )
+    }
+
+    /// Returns true if this source is still read in offline mode, as it doesn't depend on the
+    /// filesystem, environment or network of the build
+    fn works_offline(self) -> bool {
+        matches!(self, Source::Epoch | Source::Clock | Source::Default)
+    }
+
+    /// Reads the source named by `arg`, which is only `None` if it doesn't take an argument
+    fn read(self, arg: Option<&LitStr>, options: &Options) -> syn::Result<Kind> {
+        let Some(arg) = arg else {
+            return match self {
+                Source::Git => Ok(git::read(git::Field::parse(options.field.as_ref())?)),
+                Source::Epoch => read_source_date_epoch(options),
+                Source::Clock => read_clock(options),
+                _ => unreachable!("the arguments are checked before they're read"),
+            };
+        };
+        match self {
+            Source::File => read_file(arg, options),
+            Source::Env => Ok(read_from_env(arg, options)),
+            Source::Http => http::read(arg, options),
+            Source::Cmd => cmd::read(arg),
+            Source::Provider => cmd::read_provider(arg, options),
+            Source::Git | Source::Epoch | Source::Clock | Source::Default => {
+                unreachable!("this source doesn't take an argument")
+            }
+        }
+    }
+
+    /// What the argument naming this source is, for diagnostics about the macro's arguments
+    fn argument(self) -> &'static str {
+        match self {
+            Source::File => "the filename",
+            Source::Env => "the environment variable name",
+            Source::Http => "the URL",
+            Source::Cmd => "the command",
+            Source::Provider => "the secret name",
+            Source::Git | Source::Epoch | Source::Clock | Source::Default => "nothing",
+        }
+    }
+
+    /// The argument naming this source in an example invocation
+    fn placeholder(self) -> &'static str {
+        match self {
+            Source::File => "\"filename\"",
+            Source::Env => "\"ENV_NAME\"",
+            Source::Http => "\"https://example.com\"",
+            Source::Cmd => "\"cmd:command\"",
+            Source::Provider => "\"provider:name\"",
+            Source::Git | Source::Epoch | Source::Clock | Source::Default => "",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Source::File => "file",
+            Source::Env => "environment variable",
+            Source::Http => "URL",
+            Source::Cmd => "command",
+            Source::Provider => "secret provider",
+            Source::Git => "git repository",
+            Source::Epoch => "environment variable",
+            Source::Clock => "current time",
+            Source::Default => "default",
+        }
+    }
+
+    fn not_found(self, name: &str) -> String {
+        match self {
+            Source::File => format!("No file found at {}", name),
+            Source::Env => format!("No environment variable found with name {}", name),
+            Source::Http => format!("Unable to fetch {}", name),
+            Source::Cmd => format!("The command {} failed", name),
+            Source::Provider => format!("The secret provider failed to fetch {}", name),
+            Source::Git => format!("No git metadata found in {}", name),
+            Source::Epoch => format!("No environment variable found with name {}", name),
+            Source::Clock => "The current time is unavailable".to_string(),
+            Source::Default => "No default supplied".to_string(),
+        }
+    }
+
+    fn manifest_name(self) -> &'static str {
+        match self {
+            Source::File => "file",
+            Source::Env => "env",
+            Source::Http => "http",
+            Source::Cmd => "cmd",
+            Source::Provider => "provider",
+            Source::Git => "git",
+            Source::Epoch => "env",
+            Source::Clock => "clock",
+            Source::Default => "default",
+        }
+    }
+
+    fn runtime_path(self) -> proc_macro2::TokenStream {
+        match self {
+            Source::File => quote!(::file_env_const_runtime::Source::File),
+            Source::Env => quote!(::file_env_const_runtime::Source::Env),
+            Source::Http => quote!(::file_env_const_runtime::Source::Http),
+            Source::Cmd => quote!(::file_env_const_runtime::Source::Cmd),
+            Source::Provider => quote!(::file_env_const_runtime::Source::Provider),
+            Source::Git => quote!(::file_env_const_runtime::Source::Git),
+            Source::Epoch => quote!(::file_env_const_runtime::Source::Env),
+            Source::Clock => quote!(::file_env_const_runtime::Source::Clock),
+            Source::Default => quote!(::file_env_const_runtime::Source::Default),
+        }
+    }
 }
 
 /// Loads an environment variable, falling back to a file, falling back to a default value, all at
@@ -84,35 +940,10 @@ enum Kind {
 /// ```
 #[proc_macro]
 pub fn env_file(input: TokenStream) -> TokenStream {
-    let parser = Punctuated::<LitStr, Token![,]>::parse_separated_nonempty;
-    let mut l = parser.parse(input.clone()).unwrap().into_iter();
-
-    match read_from_env(&mut l) {
-        Kind::Data(data) => return data.into_token_stream().into(),
-        #[cfg(feature = "log")]
-        Kind::Name(name) => eprintln!(
-            "No environment variable found with name {}, trying file",
-            name
-        ),
-        #[cfg(not(feature = "log"))]
-        Kind::Name(_) => {}
-    }
-
-    match read_file(&mut l) {
-        Kind::Data(data) => return data.into_token_stream().into(),
-        #[cfg(feature = "log")]
-        Kind::Name(name) => eprintln!("No file found at {}, trying default", name),
-        #[cfg(not(feature = "log"))]
-        Kind::Name(_) => {}
-    };
-
-    if let Some(data) = l.next() {
-        data.into_token_stream().into()
-    } else {
-        panic!(
-            r#"No filename argument supplied, try file_env!("filename", "ENV_NAME", "default_value")"#
-        );
-    }
+    resolve("env_file", input, &[Source::Env, Source::File])
+        .and_then(|resolution| resolution.embed(Resolved::value))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
 /// Loads a file, falling back to an environment variable, falling back to a default value, all at
@@ -143,65 +974,1868 @@ pub fn env_file(input: TokenStream) -> TokenStream {
 /// ```
 #[proc_macro]
 pub fn file_env(input: TokenStream) -> TokenStream {
-    let parser = Punctuated::<LitStr, Token![,]>::parse_separated_nonempty;
-    let mut l = parser.parse(input.clone()).unwrap().into_iter();
-
-    match read_file(&mut l) {
-        Kind::Data(data) => return data.into_token_stream().into(),
-        #[cfg(feature = "log")]
-        Kind::Name(name) => eprintln!("No file found at {}, trying environment variable", name),
-        #[cfg(not(feature = "log"))]
-        Kind::Name(_) => {}
-    };
+    resolve("file_env", input, &[Source::File, Source::Env])
+        .and_then(|resolution| resolution.embed(Resolved::value))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    match read_from_env(&mut l) {
-        Kind::Data(data) => return data.into_token_stream().into(),
-        #[cfg(feature = "log")]
-        Kind::Name(name) => eprintln!(
-            "No environment variable found with name {}, trying default",
-            name
-        ),
-        #[cfg(not(feature = "log"))]
-        Kind::Name(_) => {}
-    }
+/// The same as [`env_file!`], but expands to a tuple of the value and the
+/// `file_env_const_runtime::Source` it was loaded from
+///
+/// # Examples
+/// ```
+///# use file_env_const::env_file_with_source;
+/// use file_env_const_runtime::Source;
+///
+/// const ENV_DATA: (&'static str, Source) = env_file_with_source!("CARGO_PKG_NAME", "Cargo.toml");
+/// assert_eq!(ENV_DATA, ("file_env_const", Source::Env));
+///
+/// const DEFAULT_DATA: (&'static str, Source) =
+///     env_file_with_source!("ENV_NOT_FOUND", "no_such_file", "fallback_string");
+/// assert!(DEFAULT_DATA.1.is_default());
+/// ```
+#[proc_macro]
+pub fn env_file_with_source(input: TokenStream) -> TokenStream {
+    resolve("env_file_with_source", input, &[Source::Env, Source::File])
+        .and_then(|resolution| {
+            resolution.embed(|resolved| {
+                let value = resolved.value()?;
+                let source = resolved.source.runtime_path();
+                Ok(quote!((#value, #source)))
+            })
+        })
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    if let Some(data) = l.next() {
-        data.into_token_stream().into()
-    } else {
-        panic!(
-            r#"No filename argument supplied, try file_env!("filename", "ENV_NAME", "default_value")"#
-        );
-    }
+/// The same as [`file_env!`], but expands to a tuple of the value and the
+/// `file_env_const_runtime::Source` it was loaded from
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_with_source;
+/// use file_env_const_runtime::Source;
+///
+/// const FILE_DATA: (&'static str, Source) = file_env_with_source!("Cargo.toml", "CARGO_PKG_NAME");
+/// assert_eq!(FILE_DATA.1, Source::File);
+///
+/// const ENV_DATA: (&'static str, Source) = file_env_with_source!("no_such_file", "CARGO_PKG_NAME");
+/// assert_eq!(ENV_DATA, ("file_env_const", Source::Env));
+/// ```
+#[proc_macro]
+pub fn file_env_with_source(input: TokenStream) -> TokenStream {
+    resolve("file_env_with_source", input, &[Source::File, Source::Env])
+        .and_then(|resolution| {
+            resolution.embed(|resolved| {
+                let value = resolved.value()?;
+                let source = resolved.source.runtime_path();
+                Ok(quote!((#value, #source)))
+            })
+        })
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-fn read_file<I>(parser_list: &mut I) -> Kind
-where
-    I: Iterator<Item = LitStr>,
-{
-    if let Some(x) = parser_list.next() {
-        let mut filename = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        filename.push(x.value());
-        match std::fs::read_to_string(filename.clone()) {
-            Ok(d) => Kind::Data(LitStr::new(&d, x.span())),
+/// The same as [`file_env!`], but expands to a `&'static [u8]`, so the file doesn't need to be
+/// UTF-8
+///
+/// The `offset` and `length` options select a range of bytes, such as a fixed-size header of a
+/// firmware image.
+///
+/// The default can be a byte string, such as `b"\x00\x01"`, and on Unix an environment variable
+/// which isn't Unicode is embedded as its raw bytes.
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_bytes;
+/// const HEADER: &[u8] = file_env_bytes!("Cargo.toml", "ENV_NOT_FOUND", offset = 1, length = 9);
+/// assert_eq!(HEADER, b"workspace");
+///
+/// const FALLBACK: &[u8] = file_env_bytes!("no_such_file", "CARGO_PKG_NAME");
+/// assert_eq!(FALLBACK, b"file_env_const");
+///
+/// const MAGIC: &[u8] = file_env_bytes!("no_such_file", "ENV_NOT_FOUND", b"\x7fELF\x00");
+/// assert_eq!(MAGIC, b"\x7fELF\x00");
+///
+/// static BLOB: &[u8] = file_env_bytes!("Cargo.toml", "ENV_NOT_FOUND", align = 64);
+/// assert_eq!(BLOB.as_ptr() as usize % 64, 0);
+/// ```
+#[proc_macro]
+pub fn file_env_bytes(input: TokenStream) -> TokenStream {
+    resolve_bytes("file_env_bytes", input, &[Source::File, Source::Env])
+        .and_then(|resolution| resolution.embed(Resolved::value))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-            Err(_) => Kind::Name(filename.to_string_lossy().to_string()),
-        }
-    } else {
-        panic!("No filename argument supplied");
-    }
+/// The same as [`env_file!`], but expands to a `&'static [u8]`, so the file doesn't need to be
+/// UTF-8
+///
+/// # Examples
+/// ```
+///# use file_env_const::env_file_bytes;
+/// const DATA: &[u8] = env_file_bytes!("ENV_NOT_FOUND", "no_such_file", "fallback");
+/// assert_eq!(DATA, b"fallback");
+/// ```
+#[proc_macro]
+pub fn env_file_bytes(input: TokenStream) -> TokenStream {
+    resolve_bytes("env_file_bytes", input, &[Source::Env, Source::File])
+        .and_then(|resolution| resolution.embed(Resolved::value))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-fn read_from_env<I>(parser_list: &mut I) -> Kind
-where
-    I: Iterator<Item = LitStr>,
-{
-    if let Some(x) = parser_list.next() {
-        let env_var_name = x.value();
-        match std::env::var(env_var_name.clone()) {
-            Ok(s) => Kind::Data(LitStr::new(&s, x.span())),
-            Err(_) => Kind::Name(env_var_name),
-        }
-    } else {
-        panic!("No env argument supplied");
+/// Loads a file, falling back to an environment variable, falling back to default code, all at
+/// compile time, and splices the value into the program as Rust tokens, like `include!`
+///
+/// The first argument is a filename, the second is an environment variable, and the third
+/// (optional) is the default, which can either be a string or a braced block of code. This lets a
+/// checked-in implementation be replaced by generated code when it's available.
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_include;
+/// file_env_include!("no_such_file.rs", "GENERATED_CODE", {
+///     fn generated() -> u32 {
+///         42
+///     }
+/// });
+/// assert_eq!(generated(), 42);
+///
+/// let answer = file_env_include!("no_such_file.rs", "GENERATED_CODE", "6 * 7");
+/// assert_eq!(answer, 42);
+/// ```
+#[proc_macro]
+pub fn file_env_include(input: TokenStream) -> TokenStream {
+    resolve_include("file_env_include", input, &[Source::File, Source::Env])
+        .and_then(|resolved| resolved.tokens())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Expands to `true` if the file or the environment variable exists at compile time, and `false`
+/// otherwise
+///
+/// The arguments are the same as [`file_env!`], but without a fallback string. Options which
+/// select part of the value, such as `key`, also require that part to exist. Neither strict mode
+/// nor `required` apply, as a missing source isn't an error here. In offline mode it's always
+/// `false`.
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_exists;
+/// const HAS_MANIFEST: bool = file_env_exists!("Cargo.toml", "ENV_NOT_FOUND");
+/// assert!(HAS_MANIFEST);
+///
+/// const LICENSED: bool = file_env_exists!("license.key", "LICENSE_KEY");
+/// if !LICENSED {
+///     println!("Running in evaluation mode");
+/// }
+/// ```
+#[proc_macro]
+pub fn file_env_exists(input: TokenStream) -> TokenStream {
+    exists(input, &[Source::File, Source::Env])
+        .map(|exists| quote!(#exists))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Returns true if any source in `chain` exists
+fn exists(input: TokenStream, chain: &[Source]) -> syn::Result<bool> {
+    let args: Args = syn::parse(input)?;
+    if let Some(branch) = args.branches.first() {
+        return Err(syn::Error::new(
+            branch.span,
+            "file_env_exists! doesn't take branches, use `cfg!` around it instead",
+        ));
+    }
+    let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+    if let Some(extra) = args.values.get(arguments) {
+        return Err(syn::Error::new(
+            extra.span(),
+            "file_env_exists! doesn't take a fallback string",
+        ));
+    }
+    if let Some(expression) = &args.expression {
+        return Err(syn::Error::new_spanned(
+            expression,
+            "file_env_exists! doesn't take a default",
+        ));
+    }
+    if let Some(bytes) = &args.bytes {
+        return Err(syn::Error::new(
+            bytes.span(),
+            "file_env_exists! doesn't take a default",
+        ));
+    }
+    check_arguments("file_env_exists", &args.values, chain, false, &args.options)?;
+    if offline() {
+        return Ok(false);
+    }
+
+    for (&source, arg) in chain.iter().zip(&args.values) {
+        let source = source.for_arg(arg);
+        if let Kind::Data(data, _) = source.read(Some(arg), &args.options)? {
+            match transform::apply(data, &args.options)? {
+                transform::Transformed::Value(_) => return Ok(true),
+                transform::Transformed::Invalid(e) if !args.options.falls_back_on_invalid()? => {
+                    return Err(e)
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Resolves several sources and embeds them joined together, optionally with a separator
+///
+/// Each part is a parenthesised group taking the same arguments as [`env_file!`]: an environment
+/// variable, a filename, and an optional fallback string. Any options apply to every part, except
+/// `separator`, which is put between the parts, and the options which choose how the value is
+/// embedded, which apply to the whole.
+///
+/// # Examples
+/// ```
+///# use file_env_const::concat_env_file;
+/// const CHAIN: &'static str = concat_env_file!(
+///     ("CA_PEM", "no_such_file", "ca"),
+///     ("INTERMEDIATE_PEM", "no_such_file", "intermediate"),
+///     separator = "\n"
+/// );
+/// assert_eq!(CHAIN, "ca\nintermediate");
+/// ```
+#[proc_macro]
+pub fn concat_env_file(input: TokenStream) -> TokenStream {
+    concat(input)
+        .and_then(|resolved| resolved.value())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Resolves each part of a `concat_env_file!` invocation, and joins them
+fn concat(input: TokenStream) -> syn::Result<Resolved> {
+    let ConcatArgs { parts, mut options } = syn::parse(input)?;
+    let separator = options
+        .separator
+        .take()
+        .map(|s| s.value())
+        .unwrap_or_default();
+    if let Some(expected) = &options.expect_sha256 {
+        return Err(syn::Error::new(
+            expected.span(),
+            "`expect_sha256` can't be used with concat_env_file!, as each part has its own digest",
+        ));
+    }
+    if let Some(placeholder) = &options.dev_placeholder {
+        return Err(syn::Error::new(
+            placeholder.span(),
+            "`dev_placeholder` can't be used with concat_env_file!, use `cfg!` around it instead",
+        ));
+    }
+
+    let mut joined = Vec::new();
+    for (i, values) in parts.into_iter().enumerate() {
+        let args = Args {
+            values,
+            code: None,
+            branches: Vec::new(),
+            default: None,
+            expression: None,
+            default_expression: None,
+            bytes: None,
+            default_bytes: None,
+            options: options.clone(),
+            tokens: proc_macro2::TokenStream::new(),
+        };
+        let part = resolve_args("concat_env_file", args, &[Source::Env, Source::File])?;
+        if i > 0 {
+            joined.extend_from_slice(separator.as_bytes());
+        }
+        joined.extend_from_slice(&part.data.bytes);
+    }
+    Ok(Resolved {
+        data: Data {
+            bytes: joined,
+            span: Span::call_site(),
+        },
+        // The parts each have their own source
+        source: Source::Default,
+        path: None,
+        expression: None,
+        options,
+    })
+}
+
+/// Loads a file, falling back to an environment variable, falling back to a default value, all at
+/// compile time, and splits the value by a pattern into several `&'static str` constants
+///
+/// The first argument is the pattern, followed by `<-` and the same arguments as [`file_env!`]. In
+/// the pattern, each upper case identifier names a constant, and the text between them must appear
+/// in the value, so `"HOST:PORT"` defines `HOST` and `PORT`. Each constant takes the value up to the
+/// next occurrence of the text after it, and the last takes the rest. A value which doesn't have
+/// the pattern's shape, including one where a constant would be empty, is a compile error. The
+/// pattern can be preceded by a visibility such as `pub`, which applies to every constant.
+///
+/// Options which change the value apply before it's split, and `intern` applies to each constant.
+/// The options which embed something other than a string, like `obfuscate`, can't be used.
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_split;
+/// file_env_split!("HOST:PORT" <- "no_such_file", "ENV_NOT_FOUND", "localhost:8080");
+/// assert_eq!((HOST, PORT), ("localhost", "8080"));
+///
+/// file_env_split!(pub "postgres://USER@DB_HOST/DB_NAME" <- "no_such_file", "DATABASE_URL",
+///     "postgres://app@db.internal/orders");
+/// assert_eq!((USER, DB_HOST, DB_NAME), ("app", "db.internal", "orders"));
+/// ```
+///
+/// ```compile_fail
+///# use file_env_const::file_env_split;
+/// file_env_split!("HOST:PORT" <- "no_such_file", "ENV_NOT_FOUND", "localhost");
+/// ```
+#[proc_macro]
+pub fn file_env_split(input: TokenStream) -> TokenStream {
+    split::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Defines several `&'static str` constants, each loaded from its own chain of files and
+/// environment variables, falling back to a default value, all at compile time
+///
+/// Each item names a constant and lists its sources separated by `|`, each `env "VAR"` or
+/// `file "path"`, tried in the order they're written, with an optional default string at the end.
+/// Options can follow, separated by commas, and apply to that item only. Items can have doc
+/// comments, attributes and a visibility such as `pub`. This keeps the configuration of a crate in
+/// one block which is easy to audit, rather than in many separate invocations. The options which
+/// embed something other than a string, like `obfuscate`, can't be used.
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_consts;
+/// file_env_consts! {
+///     /// Where the API is served
+///     pub API_URL: env "ENV_NOT_FOUND" | file "no_such_file" | "https://localhost";
+///     BUILD_CHANNEL: env "ENV_NOT_FOUND" | "dev", trim;
+///     NAME: file "no_such_file" | env "CARGO_PKG_NAME";
+/// }
+/// assert_eq!(API_URL, "https://localhost");
+/// assert_eq!(BUILD_CHANNEL, "dev");
+/// assert_eq!(NAME, "file_env_const");
+/// ```
+#[proc_macro]
+pub fn file_env_consts(input: TokenStream) -> TokenStream {
+    consts::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Collects every build time environment variable whose name starts with a prefix into a
+/// `&'static [(&'static str, &'static str)]` of names and values, sorted by name
+///
+/// The first argument is the prefix, after any `env_prefix` in `file_env_const.toml`, and the
+/// second (optional) is a `.env` style file of `NAME=value` lines, read like the `key` option,
+/// which gives defaults for variables which aren't set. This embeds a snapshot of feature flag
+/// style configuration without listing every flag. In offline mode the slice is empty.
+///
+/// # Examples
+/// ```
+///# use file_env_const::env_prefix_map;
+/// const CARGO: &[(&str, &str)] = env_prefix_map!("CARGO_PKG_NAME");
+/// assert_eq!(CARGO, &[("CARGO_PKG_NAME", "file_env_const")]);
+///
+/// const FLAGS: &[(&str, &str)] = env_prefix_map!("MYAPP_FLAG_");
+/// let enabled = FLAGS.iter().filter(|(_, value)| *value == "1").count();
+/// ```
+#[proc_macro]
+pub fn env_prefix_map(input: TokenStream) -> TokenStream {
+    prefix_map(input)
+        .map(|pairs| {
+            let pairs = pairs.iter().map(|(name, value)| quote!((#name, #value)));
+            quote!(&[#(#pairs),*])
+        })
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Returns the names and values of the environment variables with the prefix in `input`, with
+/// defaults from the file if one is given, sorted by name
+fn prefix_map(input: TokenStream) -> syn::Result<Vec<(String, String)>> {
+    let args: Args = syn::parse(input)?;
+    if let Some(branch) = args.branches.first() {
+        return Err(syn::Error::new(
+            branch.span,
+            "env_prefix_map! doesn't take branches, use `cfg!` around it instead",
+        ));
+    }
+    let (prefix, file) = match args.values.as_slice() {
+        [prefix] => (prefix, None),
+        [prefix, file] => (prefix, Some(file)),
+        [] => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                r#"No prefix supplied, try env_prefix_map!("MYAPP_", "defaults.env")"#,
+            ))
+        }
+        [_, _, extra, ..] => {
+            return Err(syn::Error::new(
+                extra.span(),
+                "env_prefix_map! takes a prefix and an optional file of defaults",
+            ))
+        }
+    };
+    let options = &args.options;
+    if offline() {
+        return Ok(Vec::new());
+    }
+
+    let prefix_name = options.env_name(&prefix.value());
+    let mut pairs: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with(&prefix_name))
+        .collect();
+    pairs.sort();
+    if !pairs.is_empty() {
+        reproducible::Mode::from_env()?.check(
+            reproducible::non_hermetic(Source::Env, None, options).as_deref(),
+            &format!("{}*", prefix_name),
+            prefix.span(),
+        )?;
+    }
+    let content: String = pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}\n", name, value))
+        .collect();
+    manifest::append(&manifest::Record {
+        macro_name: "env_prefix_map",
+        source: Source::Env,
+        name: Some(&format!("{}*", prefix_name)),
+        content: content.as_bytes(),
+        secret: options.secret,
+        hermetic: false,
+        looked_up: &[],
+    });
+
+    if let Some(file) = file {
+        let (data, path) = match read_file(file, options)? {
+            Kind::Data(data, path) => (data, path),
+            Kind::Name(path, span) => {
+                return Err(syn::Error::new(span, Source::File.not_found(&path)))
+            }
+        };
+        manifest::append(&manifest::Record {
+            macro_name: "env_prefix_map",
+            source: Source::File,
+            name: Some(&path),
+            content: &data.bytes,
+            secret: options.secret,
+            hermetic: reproducible::non_hermetic(Source::File, Some(&path), options).is_none(),
+            looked_up: &[],
+        });
+        for (name, value) in transform::key_values(data.as_str()?) {
+            if !pairs.iter().any(|(set, _)| *set == name) {
+                pairs.push((name, value));
+            }
+        }
+        pairs.sort();
+    }
+    Ok(pairs)
+}
+
+/// Loads a file, falling back to an environment variable, falling back to a default value, all at
+/// compile time, and embeds the hex digest of the value rather than the value itself
+///
+/// The arguments are the same as [`file_env!`], with an optional `algorithm` option, which is
+/// currently always `"sha256"`
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_env_hash;
+/// const HASH: &'static str = file_env_hash!("no_such_file", "ENV_NOT_FOUND", "abc");
+/// assert_eq!(
+///     HASH,
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+///
+/// const CARGO_HASH: &'static str = file_env_hash!("Cargo.toml", "CARGO_PKG_NAME", algorithm = "sha256");
+/// assert_eq!(CARGO_HASH.len(), 64);
+/// ```
+#[proc_macro]
+pub fn file_env_hash(input: TokenStream) -> TokenStream {
+    resolve("file_env_hash", input, &[Source::File, Source::Env])
+        .and_then(|resolution| resolution.embed(Resolved::digest))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Loads an environment variable, falling back to metadata about the current commit read from the
+/// git repository, falling back to a default value, all at compile time
+///
+/// The first argument is an environment variable, such as one CI sets to the commit being built,
+/// and the second (optional) is a fallback string. The `field` option picks what is read from the
+/// repository: `"sha"` (the default), `"short_sha"`, `"branch"` or `"tag"`, which is a tag
+/// pointing at the current commit. `.git` is read directly rather than running `git`, so
+/// `git describe` output and the dirty flag aren't available, and asking for them is a compile
+/// error. The value is only read again when the crate is rebuilt, which with the
+/// `nightly-tracking` feature includes whenever `HEAD` or the ref it points at changes.
+///
+/// # Examples
+/// ```
+///# use file_env_const::git_env;
+/// const SHA: &'static str = git_env!("ENV_NOT_FOUND", "unknown", field = "short_sha");
+/// assert!(SHA == "unknown" || SHA.len() == 7);
+///
+/// const FROM_ENV: &'static str = git_env!("CARGO_PKG_NAME", field = "branch");
+/// assert_eq!(FROM_ENV, "file_env_const");
+/// ```
+#[proc_macro]
+pub fn git_env(input: TokenStream) -> TokenStream {
+    resolve("git_env", input, &[Source::Env, Source::Git])
+        .and_then(|resolution| resolution.embed(Resolved::value))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Embeds the build time, taken from `SOURCE_DATE_EPOCH` if it's set, falling back to an
+/// environment variable, falling back to the current time
+///
+/// The only argument is the environment variable, whose value is embedded unchanged. Otherwise the
+/// time is formatted in UTC according to the `format` option: `"rfc3339"` (the default), `"unix"`
+/// for the number of seconds since the epoch, or a pattern using `%Y`, `%m`, `%d`, `%H`, `%M`,
+/// `%S`, `%s` and `%%`. Setting `SOURCE_DATE_EPOCH` makes the timestamp reproducible.
+///
+/// # Examples
+/// ```
+///# use file_env_const::build_time_env;
+/// const BUILT: &'static str = build_time_env!("BUILD_TIME");
+/// assert_eq!(BUILT.len(), "2024-01-01T00:00:00Z".len());
+///
+/// const BUILT_ON: &'static str = build_time_env!("BUILD_DATE", format = "%Y-%m-%d");
+/// assert_eq!(&BUILT_ON[4..5], "-");
+/// ```
+#[proc_macro]
+pub fn build_time_env(input: TokenStream) -> TokenStream {
+    resolve(
+        "build_time_env",
+        input,
+        &[Source::Epoch, Source::Env, Source::Clock],
+    )
+    .and_then(|resolution| resolution.embed(Resolved::value))
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Reads the size or modification time of a file, falling back to an environment variable,
+/// falling back to a default value, all at compile time, and embeds it as a `u64`
+///
+/// The arguments are the same as [`file_env!`], with the environment variable and default giving
+/// the number instead. The `field` option picks what is read from the file: `"mtime"` (the
+/// default), the modification time in seconds since the Unix epoch, or `"size"`, its length in
+/// bytes. This is enough to fingerprint a large asset without hashing it, but modification times
+/// differ between checkouts, so they aren't reproducible.
+///
+/// # Examples
+/// ```
+///# use file_env_const::file_meta_env;
+/// const MANIFEST_LEN: u64 = file_meta_env!("Cargo.toml", "CARGO_TOML_LEN", field = "size");
+/// assert_eq!(MANIFEST_LEN, std::fs::metadata("Cargo.toml").unwrap().len());
+///
+/// const MTIME: u64 = file_meta_env!("no_such_file", "ENV_NOT_FOUND", "1700000000");
+/// assert_eq!(MTIME, 1700000000);
+/// ```
+#[proc_macro]
+pub fn file_meta_env(input: TokenStream) -> TokenStream {
+    syn::parse(input)
+        .and_then(|mut args: Args| {
+            args.options.meta = true;
+            resolve_branches("file_meta_env", args, &[Source::File, Source::Env])
+        })
+        .and_then(|resolution| resolution.embed(Resolved::value))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates a struct and a constant of it from a TOML file, filling each field at compile time
+///
+/// The first item declares the constant and the file, relative like the filenames of the other
+/// macros. It can be followed by the definition of the struct, whose fields are filled from the
+/// keys with the same names, or the dotted path given by a `#[key = "..."]` attribute. A field can
+/// be `&'static str`, `bool`, `char`, any integer or float type, or an `Option` of one of these,
+/// which is `None` if the key is missing. An `#[env = "VAR"]` attribute overrides the field with
+/// the build time environment variable `VAR`, after any `env_prefix` in `file_env_const.toml`,
+/// when it's set.
+///
+/// Without a struct definition, a struct deriving `Clone`, `Copy`, `Debug` and `PartialEq` is
+/// generated with a field for each top-level string, integer, float or boolean, as a
+/// `&'static str`, `i64`, `f64` or `bool`. Keys which aren't identifiers once `-` is replaced by
+/// `_` are left out.
+///
+/// This requires the `toml` feature.
+///
+/// # Examples
+/// ```ignore
+///# use file_env_const::toml_const;
+/// toml_const! {
+///     /// The settings in config.toml
+///     pub const CONFIG: Config = "config.toml";
+///
+///     pub struct Config {
+///         pub name: &'static str,
+///         #[env = "APP_PORT"]
+///         pub port: u16,
+///         #[key = "server.timeout"]
+///         pub timeout: f64,
+///         pub debug: Option<bool>,
+///     }
+/// }
+///
+/// toml_const! {
+///     const PACKAGE: Package = "package.toml";
+/// }
+/// ```
+#[proc_macro]
+pub fn toml_const(input: TokenStream) -> TokenStream {
+    toml_const::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Defines constants describing the value of the constant it's attached to
+///
+/// The constant must be initialized by `file_env!`, `env_file!`, `file_env_bytes!` or
+/// `env_file_bytes!`, which the attribute expands itself. It's followed by `<NAME>_LEN`, the
+/// value's length in bytes, `<NAME>_LINES`, its number of lines, and `<NAME>_IS_DEFAULT`, which is
+/// true if none of the sources were found, all computed while expanding. The length and lines are
+/// of the value before any embedding option like `compress` is applied.
+///
+/// # Examples
+/// ```
+///# use file_env_const::{file_env, metadata};
+/// #[metadata]
+/// const GREETING: &str = file_env!("no_such_file", "ENV_NOT_FOUND", "Hello\nWorld\n");
+/// assert_eq!(GREETING_LEN, 12);
+/// assert_eq!(GREETING_LINES, 2);
+/// assert!(GREETING_IS_DEFAULT);
+///
+/// let mut buffer = [0u8; GREETING_LEN];
+/// buffer.copy_from_slice(GREETING.as_bytes());
+/// ```
+#[proc_macro_attribute]
+pub fn metadata(attr: TokenStream, item: TokenStream) -> TokenStream {
+    metadata::expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The values found for a macro invocation
+enum Resolution {
+    Value(Resolved),
+    /// The value found for each branch's `cfg` predicate, and the default used if none of them
+    /// hold
+    Branches(Vec<(proc_macro2::TokenStream, Resolved)>, Option<Resolved>),
+    /// The `dev_placeholder` used in builds with debug assertions, and the invocation without it
+    /// which resolves the value in other builds. The invocation is behind `#[cfg]` rather than
+    /// `cfg!`, so debug builds strip it before it's expanded and never read the sources
+    Placeholder(Resolved, proc_macro2::TokenStream),
+}
+
+impl Resolution {
+    /// Returns the tokens to embed the value with `embed`, choosing between the branches with
+    /// `cfg!`
+    ///
+    /// A placeholder's invocation embeds the value the same way, as it's the same macro.
+    fn embed(
+        &self,
+        embed: impl Fn(&Resolved) -> syn::Result<proc_macro2::TokenStream>,
+    ) -> syn::Result<proc_macro2::TokenStream> {
+        let (branches, default) = match self {
+            Resolution::Value(resolved) => return embed(resolved),
+            Resolution::Branches(branches, default) => (branches, default),
+            Resolution::Placeholder(placeholder, invocation) => {
+                let placeholder = embed(placeholder)?;
+                let value = Ident::new("value", Span::mixed_site());
+                return Ok(quote!({
+                    #[cfg(debug_assertions)]
+                    let #value = #placeholder;
+                    #[cfg(not(debug_assertions))]
+                    let #value = #invocation;
+                    #value
+                }));
+            }
+        };
+        let mut expansion = match default {
+            Some(default) => {
+                let value = embed(default)?;
+                quote!({ #value })
+            }
+            None => quote!({
+                ::core::panic!("None of the branches apply to this build, and there's no default")
+            }),
+        };
+        for (condition, resolved) in branches.iter().rev() {
+            let value = embed(resolved)?;
+            expansion = quote!(if ::core::cfg!(#condition) { #value } else #expansion);
+        }
+        Ok(expansion)
+    }
+}
+
+/// A value which has been found, and how to embed it
+struct Resolved {
+    data: Data,
+    source: Source,
+    /// The file the data was read from unchanged, if it was
+    path: Option<PathBuf>,
+    /// The expression embedded instead of the data, if the default is an expression and none of the
+    /// sources were found
+    expression: Option<proc_macro2::TokenStream>,
+    options: Options,
+}
+
+impl Resolved {
+    /// Returns the tokens to embed the value
+    fn value(&self) -> syn::Result<proc_macro2::TokenStream> {
+        if let Some(algorithm) = &self.options.algorithm {
+            return Err(syn::Error::new(
+                algorithm.span(),
+                "`algorithm` is only used by file_env_hash!",
+            ));
+        }
+        if let Some(expression) = &self.expression {
+            return self.embed_expression(expression);
+        }
+        if self.options.bytes {
+            embed::embed_bytes(&self.data, self.path.as_deref(), &self.options)
+        } else {
+            let data = LitStr::new(self.data.as_str()?, self.data.span);
+            embed::embed(&data, self.path.as_deref(), &self.options)
+        }
+    }
+
+    /// Returns the tokens to embed a default expression, which options can't change as its value
+    /// is only known when the expansion is compiled
+    fn embed_expression(
+        &self,
+        expression: &proc_macro2::TokenStream,
+    ) -> syn::Result<proc_macro2::TokenStream> {
+        let embedding = [
+            ("obfuscate", self.options.obfuscate),
+            ("encrypt", self.options.encrypt.is_some()),
+            ("compress", self.options.compress.is_some()),
+            ("intern", self.options.intern),
+            ("deserialize", self.options.deserialize.is_some()),
+            ("epoch", self.options.epoch),
+        ];
+        if let Some((option, _)) = embedding.iter().find(|(_, requested)| *requested) {
+            return Err(syn::Error::new_spanned(
+                expression,
+                format!(
+                    "`{}` can't be used with a default expression, as its value isn't known \
+                     until it's compiled",
+                    option
+                ),
+            ));
+        }
+        Ok(quote!({ #expression }))
+    }
+
+    /// Returns the value parsed as Rust tokens, for `file_env_include!`
+    fn tokens(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let embedding = [
+            ("obfuscate", self.options.obfuscate),
+            ("encrypt", self.options.encrypt.is_some()),
+            ("compress", self.options.compress.is_some()),
+            ("intern", self.options.intern),
+            ("deserialize", self.options.deserialize.is_some()),
+            ("epoch", self.options.epoch),
+        ];
+        if let Some((option, _)) = embedding.iter().find(|(_, requested)| *requested) {
+            return Err(syn::Error::new(
+                self.data.span,
+                format!("`{}` can't be used with file_env_include!", option),
+            ));
+        }
+        self.data.as_str()?.parse().map_err(|e| {
+            syn::Error::new(
+                self.data.span,
+                format!("The value couldn't be parsed as Rust tokens: {}", e),
+            )
+        })
+    }
+
+    /// Returns the tokens to embed the hex digest of the value
+    fn digest(&self) -> syn::Result<proc_macro2::TokenStream> {
+        if let Some(expression) = &self.expression {
+            return Err(syn::Error::new_spanned(
+                expression,
+                "file_env_hash! needs a default string to hash, not an expression",
+            ));
+        }
+        if let Some(algorithm) = &self.options.algorithm {
+            if algorithm.value() != "sha256" {
+                return Err(syn::Error::new(
+                    algorithm.span(),
+                    format!(
+                        "Unsupported hash algorithm {}, only sha256 is supported",
+                        algorithm.value()
+                    ),
+                ));
+            }
+        }
+        let digest = sha256::hex_digest(&self.data.bytes);
+        let digest = LitStr::new(&digest, self.data.span);
+        embed::embed(&digest, None, &self.options)
     }
 }
+
+/// Tries each source in `chain` in turn, falling back to the default if none of them are found
+///
+/// The result is recorded in the audit manifest under `macro_name`. In strict mode any fallback is
+/// an error, and if the `required` option is given then falling back from the first source is an
+/// error.
+fn resolve(macro_name: &str, input: TokenStream, chain: &[Source]) -> syn::Result<Resolution> {
+    resolve_branches(macro_name, syn::parse(input)?, chain)
+}
+
+/// The same as [`resolve`], but for a value which is parsed as Rust tokens
+fn resolve_include(
+    macro_name: &str,
+    input: TokenStream,
+    chain: &[Source],
+) -> syn::Result<Resolved> {
+    let mut args: Args = syn::parse(input)?;
+    if let Some(branch) = args.branches.first() {
+        return Err(syn::Error::new(
+            branch.span,
+            "file_env_include! doesn't take branches, as code can't be chosen by `cfg!`",
+        ));
+    }
+    if let Some(expression) = &args.expression {
+        return Err(syn::Error::new_spanned(
+            expression,
+            "file_env_include! takes a braced block of code as the default, not an expression",
+        ));
+    }
+    if let Some(placeholder) = &args.options.dev_placeholder {
+        return Err(syn::Error::new(
+            placeholder.span(),
+            "`dev_placeholder` can't be used with file_env_include!, as a placeholder for code \
+             would rarely compile",
+        ));
+    }
+    args.options.include = true;
+    resolve_args(macro_name, args, chain)
+}
+
+/// The same as [`resolve`], but resolving the value as bytes rather than a string
+fn resolve_bytes(
+    macro_name: &str,
+    input: TokenStream,
+    chain: &[Source],
+) -> syn::Result<Resolution> {
+    let mut args: Args = syn::parse(input)?;
+    args.options.bytes = true;
+    resolve_branches(macro_name, args, chain)
+}
+
+/// Resolves `args`, resolving each branch separately if there are branches
+///
+/// Every branch is resolved whichever profile and target are being built, as only the expansion
+/// can tell which applies, so strict mode and `required` apply to all of them. Nothing is
+/// resolved if there's a `dev_placeholder`, as that's left to an invocation of `macro_name`
+/// without it, which is only expanded in builds without debug assertions.
+fn resolve_branches(macro_name: &str, args: Args, chain: &[Source]) -> syn::Result<Resolution> {
+    if let Some(placeholder) = &args.options.dev_placeholder {
+        let resolved = Resolved {
+            data: Data::from_lit(placeholder),
+            source: Source::Default,
+            path: None,
+            expression: None,
+            options: args.options.clone(),
+        };
+        let name = Ident::new(macro_name, Span::call_site());
+        let tokens = args::without_placeholder(args.tokens);
+        let invocation = quote!(::file_env_const::#name! { #tokens });
+        return Ok(Resolution::Placeholder(resolved, invocation));
+    }
+    if args.branches.is_empty() {
+        let default = args
+            .default
+            .as_ref()
+            .map(LitStr::span)
+            .or_else(|| args.default_expression.as_ref().map(Spanned::span))
+            .or_else(|| args.default_bytes.as_ref().map(LitByteStr::span));
+        if let Some(span) = default {
+            return Err(syn::Error::new(
+                span,
+                "`default = ...` is only used with branches, otherwise the fallback string is the \
+                 last argument",
+            ));
+        }
+        return resolve_args(macro_name, args, chain).map(Resolution::Value);
+    }
+    let value = args.values.first().map(LitStr::span);
+    let value = value.or_else(|| args.expression.as_ref().map(Spanned::span));
+    let value = value.or_else(|| args.bytes.as_ref().map(LitByteStr::span));
+    if let Some(span) = value {
+        return Err(syn::Error::new(
+            span,
+            "Give either string arguments or branches, not both",
+        ));
+    }
+
+    let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+    let mut branches = Vec::new();
+    for branch in args.branches {
+        if branch.values.len() < arguments || branch.values.len() > arguments + 1 {
+            return Err(syn::Error::new(
+                branch.span,
+                format!(
+                    "Each branch of {}! takes {} string arguments and an optional fallback string",
+                    macro_name, arguments
+                ),
+            ));
+        }
+        let mut values = branch.values;
+        let mut expression = None;
+        let mut bytes = None;
+        if values.len() == arguments {
+            values.extend(args.default.clone());
+            expression = args.default_expression.clone();
+            bytes = args.default_bytes.clone();
+        }
+        let branch_args = Args {
+            values,
+            code: None,
+            branches: Vec::new(),
+            default: None,
+            expression,
+            default_expression: None,
+            bytes,
+            default_bytes: None,
+            options: args.options.clone(),
+            tokens: proc_macro2::TokenStream::new(),
+        };
+        let resolved = resolve_args(macro_name, branch_args, chain)?;
+        branches.push((branch.condition, resolved));
+    }
+    let default = match (args.default, args.default_expression, args.default_bytes) {
+        (None, None, Some(bytes)) => Some(Resolved {
+            data: Data {
+                bytes: bytes.value(),
+                span: bytes.span(),
+            },
+            source: Source::Default,
+            path: None,
+            expression: None,
+            options: args.options,
+        }),
+        (Some(default), _, _) => Some(Resolved {
+            data: Data::from_lit(&default),
+            source: Source::Default,
+            path: None,
+            expression: None,
+            options: args.options,
+        }),
+        (None, Some(expression), _) => Some(Resolved {
+            data: Data::text("", expression.span()),
+            source: Source::Default,
+            path: None,
+            expression: Some(expression),
+            options: args.options,
+        }),
+        (None, None, None) => None,
+    };
+    Ok(Resolution::Branches(branches, default))
+}
+
+fn resolve_args(macro_name: &str, args: Args, chain: &[Source]) -> syn::Result<Resolved> {
+    let Args {
+        mut values,
+        code,
+        expression,
+        bytes,
+        options,
+        ..
+    } = args;
+    check_arguments(macro_name, &values, chain, true, &options)?;
+    let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+    if let Some(extra) = values.get(arguments + 1) {
+        return Err(syn::Error::new(
+            extra.span(),
+            format!(
+                "The {} argument of {}! is one too many, as it takes {}, then a default, as in {}",
+                ordinal(arguments + 2),
+                macro_name,
+                describe_arguments(chain),
+                usage(macro_name, chain, true)
+            ),
+        ));
+    }
+    if let Some(bytes) = &bytes {
+        let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+        if !options.bytes {
+            return Err(syn::Error::new(
+                bytes.span(),
+                "A byte string default is only used by the bytes macros",
+            ));
+        }
+        if values.len() != arguments {
+            return Err(syn::Error::new(
+                bytes.span(),
+                format!(
+                    "A byte string default replaces the default string, so it must follow exactly \
+                     {} string arguments",
+                    arguments
+                ),
+            ));
+        }
+        // Stands in for the default, so that find knows there is one
+        values.push(LitStr::new("", bytes.span()));
+    }
+    if let Some(expression) = &expression {
+        let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+        if values.len() != arguments {
+            return Err(syn::Error::new_spanned(
+                expression,
+                format!(
+                    "A default expression replaces the default string, so it must follow exactly \
+                     {} string arguments",
+                    arguments
+                ),
+            ));
+        }
+        // Stands in for the default, so that find knows there is one
+        values.push(LitStr::new("", expression.span()));
+    }
+    if let Some(code) = code {
+        let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+        if !options.include {
+            return Err(syn::Error::new(
+                code.span(),
+                "A default block is only used by file_env_include!",
+            ));
+        }
+        if values.len() != arguments {
+            return Err(syn::Error::new(
+                code.span(),
+                format!(
+                    "A default block replaces the default string, so it must follow exactly {} \
+                     string arguments",
+                    arguments
+                ),
+            ));
+        }
+        values.push(LitStr::new(&code.stream().to_string(), code.span()));
+    }
+    if let Some(separator) = &options.separator {
+        return Err(syn::Error::new(
+            separator.span(),
+            "`separator` is only used by concat_env_file!",
+        ));
+    }
+    if let Some(field) = &options.field {
+        if options.meta {
+            MetaField::parse(Some(field))?;
+        } else if chain.contains(&Source::Git) {
+            git::Field::parse(Some(field))?;
+        } else {
+            return Err(syn::Error::new(
+                field.span(),
+                "`field` is only used by git_env! and file_meta_env!",
+            ));
+        }
+    }
+    if let Some(format) = &options.format {
+        if !chain.contains(&Source::Clock) {
+            return Err(syn::Error::new(
+                format.span(),
+                "`format` is only used by build_time_env!",
+            ));
+        }
+        time::check_format(format)?;
+    }
+    let started = Instant::now();
+    let mut attempts = Vec::new();
+    let found = find(macro_name, values, chain, &options, &mut attempts);
+    log::write_file(macro_name, &attempts, &found, started.elapsed());
+    let (mut data, source, name) = found?;
+    let looked_up: Vec<String> = attempts
+        .iter()
+        .map(|attempt| format!("{}:{}", attempt.source.manifest_name(), attempt.name))
+        .collect();
+    if let (Source::Default, Some(bytes)) = (source, &bytes) {
+        data.bytes = bytes.value();
+    }
+    if let (Source::Default, Some(expression)) = (source, expression) {
+        manifest::append(&manifest::Record {
+            macro_name,
+            source,
+            name: None,
+            content: expression.to_string().as_bytes(),
+            secret: options.secret,
+            hermetic: true,
+            looked_up: &looked_up,
+        });
+        return Ok(Resolved {
+            data,
+            source,
+            path: None,
+            expression: Some(expression),
+            options,
+        });
+    }
+
+    let path = name
+        .as_ref()
+        .filter(|_| matches!(source, Source::File) && !options.transforms())
+        .map(PathBuf::from);
+    let non_hermetic = reproducible::non_hermetic(source, name.as_deref(), &options);
+    let name = name.map(|name| options.redact(name));
+    let description = name.as_deref().unwrap_or("the default");
+
+    validate::check(&data, &options, description)?;
+    if let Some(schema) = &options.schema {
+        schema::check(&data.bytes, schema, &options, data.span)?;
+    }
+
+    if let Some(max_size) = &options.max_size {
+        let max: usize = max_size.base10_parse()?;
+        if data.bytes.len() > max {
+            return Err(syn::Error::new(
+                data.span,
+                format!(
+                    "{} is {} bytes, more than the max_size of {}",
+                    description,
+                    data.bytes.len(),
+                    max
+                ),
+            ));
+        }
+    }
+
+    if let Some(expected) = &options.expect_sha256 {
+        let actual = sha256::hex_digest(&data.bytes);
+        if !actual.eq_ignore_ascii_case(expected.value().trim()) {
+            // The digest of a secret would let it be confirmed by guessing, as in the manifest
+            let message = if options.secret {
+                format!(
+                    "The SHA-256 of {} isn't the {} which was expected",
+                    description,
+                    expected.value()
+                )
+            } else {
+                format!(
+                    "The SHA-256 of {} is {}, but {} was expected",
+                    description,
+                    actual,
+                    expected.value()
+                )
+            };
+            return Err(syn::Error::new(expected.span(), message));
+        }
+    }
+
+    // A file's signature was checked against its bytes as read, by check_file
+    if let Some(public_key) = options
+        .minisign_key
+        .as_ref()
+        .filter(|_| source != Source::File)
+    {
+        signature::verify(
+            &data.bytes,
+            None,
+            public_key,
+            options.signature_env.as_ref(),
+            description,
+        )?;
+    }
+
+    reproducible::Mode::from_env()?.check(non_hermetic.as_deref(), description, data.span)?;
+
+    manifest::append(&manifest::Record {
+        macro_name,
+        source,
+        name: name.as_deref(),
+        content: &data.bytes,
+        secret: options.secret,
+        hermetic: non_hermetic.is_none(),
+        looked_up: &looked_up,
+    });
+    Ok(Resolved {
+        data,
+        source,
+        path,
+        expression: None,
+        options,
+    })
+}
+
+/// Finds the first source in `chain` which exists, returning its data, the kind of source, and
+/// the path or variable name it was found at, if it wasn't the default
+///
+/// Each source read is added to `looked_up`, for the audit manifest and the log file.
+fn find(
+    macro_name: &str,
+    values: Vec<LitStr>,
+    chain: &[Source],
+    options: &Options,
+    looked_up: &mut Vec<log::Attempt>,
+) -> syn::Result<(Data, Source, Option<String>)> {
+    let log_level = if options.quiet {
+        log::Level::Off
+    } else {
+        log::Level::from_env()?
+    };
+    let arguments = chain.iter().filter(|s| s.takes_argument()).count();
+    let example = usage(macro_name, chain, true);
+    let mut args = values.into_iter();
+    let chain: Vec<(Source, Option<LitStr>)> = chain
+        .iter()
+        .map(|&source| {
+            let arg = if source.takes_argument() {
+                args.next()
+            } else {
+                None
+            };
+            let source = arg.as_ref().map_or(source, |arg| source.for_arg(arg));
+            (source, arg)
+        })
+        .collect();
+
+    if let Some((name, value, span)) = read_override(&chain, options) {
+        return Ok((Data::text(&value, span), Source::Env, Some(name)));
+    }
+
+    let next_sources = chain[1..]
+        .iter()
+        .map(|&(source, _)| source)
+        .chain(std::iter::once(Source::Default));
+    let offline = offline();
+    let on_invalid_fallback = options.falls_back_on_invalid()?;
+    let mut first_invalid = None;
+    for (i, (&(source, ref arg), next)) in chain.iter().zip(next_sources).enumerate() {
+        if offline && !source.works_offline() {
+            continue;
+        }
+        let kind = source.read(arg.as_ref(), options)?;
+        let (Kind::Data(_, name) | Kind::Name(name, _)) = &kind;
+        let mut attempt = log::Attempt {
+            source,
+            name: options.redact(name.clone()),
+            missing: None,
+        };
+        // Falling back from an optional source is expected, so it's never an error
+        let optional = matches!(kind, Kind::Name(..)) && source.is_optional();
+        let (not_found, span) = match kind {
+            Kind::Data(data, name) => {
+                let span = data.span;
+                match transform::apply(data, options)? {
+                    transform::Transformed::Value(data) => {
+                        let checked = if on_invalid_fallback {
+                            check_valid(&data, options)
+                        } else {
+                            Ok(())
+                        };
+                        let Err(e) = checked else {
+                            looked_up.push(attempt);
+                            if options.verify_match {
+                                verify_match(&data, source, &name, &chain[i + 1..], options)?;
+                            }
+                            return Ok((data, source, Some(name)));
+                        };
+                        let not_found = describe_invalid(source, &options.redact(name), &e);
+                        first_invalid.get_or_insert_with(|| (not_found.clone(), e.span()));
+                        (not_found, span)
+                    }
+                    transform::Transformed::Invalid(e) if on_invalid_fallback => {
+                        let not_found = describe_invalid(source, &options.redact(name), &e);
+                        first_invalid.get_or_insert_with(|| (not_found.clone(), e.span()));
+                        (not_found, span)
+                    }
+                    transform::Transformed::Invalid(e) => return Err(e),
+                    transform::Transformed::Missing(reason) => {
+                        let name = options.redact(name);
+                        let not_found = format!("The {} {} {}", source.description(), name, reason);
+                        (not_found, span)
+                    }
+                }
+            }
+            Kind::Name(name, span) => (source.not_found(&options.redact(name)), span),
+        };
+        attempt.missing = Some(not_found.clone());
+        looked_up.push(attempt);
+        if optional {
+            continue;
+        }
+
+        if strict_mode(options) {
+            return Err(failure(
+                span,
+                format!(
+                    "{}, and strict mode forbids falling back to the {}",
+                    not_found,
+                    next.description()
+                ),
+                options,
+            ));
+        }
+        if options.required && i == 0 {
+            return Err(failure(
+                span,
+                format!("{}, and it is required", not_found),
+                options,
+            ));
+        }
+        log_level.fallback(&not_found, next);
+    }
+
+    if let Some(data) = args.next() {
+        Ok((Data::from_lit(&data), Source::Default, None))
+    } else if let Some((reason, span)) = first_invalid {
+        Err(failure(
+            span,
+            format!("{}, and there's no default to use instead", reason),
+            options,
+        ))
+    } else if offline {
+        Err(failure(
+            Span::call_site(),
+            "Offline mode skips every file, environment variable and URL, but there's no \
+             default to use instead"
+                .to_string(),
+            options,
+        ))
+    } else if let Some(message) = &options.message {
+        Err(syn::Error::new(Span::call_site(), message.value()))
+    } else {
+        let span = chain
+            .iter()
+            .rev()
+            .find_map(|(_, arg)| arg.as_ref())
+            .map_or_else(Span::call_site, LitStr::span);
+        Err(syn::Error::new(
+            span,
+            format!(
+                "None of the sources of {}! were found, and there's no default to use instead, so \
+                 give one as its {} argument, as in {}",
+                macro_name,
+                ordinal(arguments + 1),
+                example
+            ),
+        ))
+    }
+}
+
+/// Describes the source `source` named `name` whose value is invalid with the error `e`, for a
+/// fallback with `on_invalid = "fallback"`
+fn describe_invalid(source: Source, name: &str, e: &syn::Error) -> String {
+    let mut reason = e.to_string();
+    if let Some(first) = reason.get_mut(0..1) {
+        first.make_ascii_lowercase();
+    }
+    format!(
+        "The {} {} is invalid: {}",
+        source.description(),
+        name,
+        reason
+    )
+}
+
+/// Checks the value of a source with the `validate`, `schema` and `deserialize` options, and that
+/// it's UTF-8 for a text macro, so that `on_invalid = "fallback"` can skip it if it's invalid
+fn check_valid(data: &Data, options: &Options) -> syn::Result<()> {
+    if !options.bytes {
+        data.as_str()?;
+    }
+    validate::check(data, options, "The value")?;
+    if let Some(schema) = &options.schema {
+        schema::check(&data.bytes, schema, options, data.span)?;
+    }
+    if let Some(format) = &options.deserialize {
+        embed::deserializer(data.as_str()?, data.span, format, options.secret)?;
+    }
+    Ok(())
+}
+
+/// Checks that every source in `rest` which exists has the same value as `data`, which was found
+/// in the source `source` named `name`, for the `verify_match` option
+fn verify_match(
+    data: &Data,
+    source: Source,
+    name: &str,
+    rest: &[(Source, Option<LitStr>)],
+    options: &Options,
+) -> syn::Result<()> {
+    for (other, arg) in rest {
+        let Some(arg) = arg else {
+            continue;
+        };
+        let Kind::Data(other_data, other_name) = other.read(Some(arg), options)? else {
+            continue;
+        };
+        let other_data = match transform::apply(other_data, options)? {
+            transform::Transformed::Value(other_data) => other_data,
+            transform::Transformed::Invalid(e) if !options.falls_back_on_invalid()? => {
+                return Err(e)
+            }
+            _ => continue,
+        };
+        if other_data.bytes == data.bytes {
+            continue;
+        }
+
+        let first = format!(
+            "{} {}",
+            source.description(),
+            options.redact(name.to_string())
+        );
+        let second = format!("{} {}", other.description(), options.redact(other_name));
+        let mut message = format!("The {} and the {} differ", first, second);
+        if !options.secret {
+            let (a, b) = (
+                String::from_utf8_lossy(&data.bytes),
+                String::from_utf8_lossy(&other_data.bytes),
+            );
+            let (mut a_lines, mut b_lines) = (a.lines(), b.lines());
+            let mut line = 1;
+            loop {
+                match (a_lines.next(), b_lines.next()) {
+                    (Some(a), Some(b)) if a == b => line += 1,
+                    (None, None) => {
+                        message.push_str(", only in their line endings or final newline");
+                        break;
+                    }
+                    (a, b) => {
+                        let quote = |s: Option<&str>| match s {
+                            Some(s) => format!("{:?}", s.chars().take(200).collect::<String>()),
+                            None => "the end".to_string(),
+                        };
+                        message = format!(
+                            "{}, first at line {}:\n- {}: {}\n+ {}: {}",
+                            message,
+                            line,
+                            first,
+                            quote(a),
+                            second,
+                            quote(b)
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+        return Err(syn::Error::new(arg.span(), message));
+    }
+    Ok(())
+}
+
+/// Checks that `values` has an argument for every source in `chain`, and that a file and an
+/// environment variable haven't been given the wrong way round
+///
+/// Arguments labelled with their kind, such as `env:API_KEY`, and the items of
+/// `file_env_consts!`, which name the kind of each source, can't be the wrong way round.
+fn check_arguments(
+    macro_name: &str,
+    values: &[LitStr],
+    chain: &[Source],
+    default: bool,
+    options: &Options,
+) -> syn::Result<()> {
+    let sources: Vec<Source> = chain
+        .iter()
+        .copied()
+        .filter(|s| s.takes_argument())
+        .collect();
+    if let Some(missing) = sources.get(values.len()) {
+        let span = values.last().map_or_else(Span::call_site, LitStr::span);
+        return Err(syn::Error::new(
+            span,
+            format!(
+                "{}! is missing its {} argument, {}, as in {}",
+                macro_name,
+                ordinal(values.len() + 1),
+                missing.argument(),
+                usage(macro_name, chain, default)
+            ),
+        ));
+    }
+
+    if macro_name == "file_env_consts" {
+        return Ok(());
+    }
+    let position = |wanted: Source| {
+        sources
+            .iter()
+            .zip(values)
+            .enumerate()
+            .find(|(_, (&source, arg))| source == wanted && !Source::is_labelled(arg))
+            .map(|(i, (_, arg))| (i, arg))
+    };
+    let (Some((j, file)), Some((i, env))) = (position(Source::File), position(Source::Env)) else {
+        return Ok(());
+    };
+    let (file_value, env_value) = (file.value(), env.value());
+    let looks_like_env = !file_value.is_empty()
+        && !file_value.starts_with(|c: char| c.is_ascii_digit())
+        && file_value
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    let looks_like_path = env_value.contains(['/', '\\', '.']);
+    if looks_like_env && looks_like_path && !path::resolve(&options.base_dir, &file_value).exists()
+    {
+        let (env_shown, file_shown) = if options.secret {
+            ("it".to_string(), format!("the {} argument", ordinal(j + 1)))
+        } else {
+            (format!("{:?}", env_value), format!("{:?}", file_value))
+        };
+        return Err(syn::Error::new(
+            env.span(),
+            format!(
+                "The {} argument of {}! is the environment variable name, but {} looks like a \
+                 path and {} looks like an environment variable name, so they may be the wrong \
+                 way round: {}! takes {}, as in {}",
+                ordinal(i + 1),
+                macro_name,
+                env_shown,
+                file_shown,
+                macro_name,
+                describe_arguments(chain),
+                usage(macro_name, chain, default)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Lists the arguments naming the sources in `chain`, such as "the filename and the environment
+/// variable name"
+fn describe_arguments(chain: &[Source]) -> String {
+    let arguments: Vec<&str> = chain
+        .iter()
+        .filter(|s| s.takes_argument())
+        .map(|s| s.argument())
+        .collect();
+    match arguments.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => "no string arguments".to_string(),
+    }
+}
+
+/// Returns an example invocation of `macro_name` with the sources in `chain`
+fn usage(macro_name: &str, chain: &[Source], default: bool) -> String {
+    let mut arguments: Vec<&str> = chain
+        .iter()
+        .filter(|s| s.takes_argument())
+        .map(|s| s.placeholder())
+        .collect();
+    if default {
+        arguments.push("\"default\"");
+    }
+    if macro_name == "file_env_consts" {
+        let sources: Vec<String> = chain
+            .iter()
+            .zip(&arguments)
+            .map(|(source, argument)| format!("{} {}", source.manifest_name(), argument))
+            .chain(default.then(|| "\"default\"".to_string()))
+            .collect();
+        return format!("{}! {{ NAME: {}; }}", macro_name, sources.join(" | "));
+    }
+    let arguments = arguments.join(", ");
+    if macro_name == "concat_env_file" {
+        format!("{}!(({}), ...)", macro_name, arguments)
+    } else {
+        format!("{}!({})", macro_name, arguments)
+    }
+}
+
+/// Returns `n` as an English ordinal, such as `2nd`
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Returns the error `reason` at `span`, followed by the `message` option on the next line
+fn failure(span: Span, reason: String, options: &Options) -> syn::Error {
+    match &options.message {
+        Some(message) => syn::Error::new(span, format!("{}\n{}", reason, message.value())),
+        None => syn::Error::new(span, reason),
+    }
+}
+
+/// Reads `FILE_ENV_CONST_OVERRIDE_<NAME>`, which wins over every source in `chain`
+///
+/// `NAME` is the `label` option, or otherwise the environment variable source in `chain`, in upper
+/// case with anything other than letters and digits replaced by `_`. Returns the override's name,
+/// its value and the span of the argument it overrides.
+fn read_override(
+    chain: &[(Source, Option<LitStr>)],
+    options: &Options,
+) -> Option<(String, String, Span)> {
+    let (name, span) = match &options.label {
+        Some(label) => (label.value(), label.span()),
+        None => chain.iter().find_map(|(source, arg)| {
+            let arg = arg.as_ref().filter(|_| *source == Source::Env)?;
+            Some((options.env_name(&arg.value()), arg.span()))
+        })?,
+    };
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = format!("FILE_ENV_CONST_OVERRIDE_{}", name);
+    let value = track::var(&name).ok()?;
+    Some((name, value, span))
+}
+
+/// Strict mode is enabled by the `strict` feature, by setting `FILE_ENV_CONST_STRICT=1` at build
+/// time, or by `strict = true` in `file_env_const.toml`
+fn strict_mode(options: &Options) -> bool {
+    options.strict || cfg!(feature = "strict") || env_flag("FILE_ENV_CONST_STRICT")
+}
+
+/// Offline mode is enabled by setting `FILE_ENV_CONST_OFFLINE=1` at build time, and on docs.rs
+fn offline() -> bool {
+    env_flag("FILE_ENV_CONST_OFFLINE") || track::var_os("DOCS_RS").is_some()
+}
+
+/// Returns true if the build time environment variable `name` is set to `1` or `true`
+fn env_flag(name: &str) -> bool {
+    track::var(name).is_ok_and(|v| v == "1" || v == "true")
+}
+
+/// Reads the file named by `x`, which must be UTF-8 unless the value is embedded as bytes
+fn read_file(x: &LitStr, options: &Options) -> syn::Result<Kind> {
+    let value = x.value();
+    let value = value.strip_prefix("file:").unwrap_or(&value);
+    let filename = if options.search_up {
+        path::search_up(&options.base_dir, value)
+    } else {
+        path::resolve(&options.base_dir, value)
+    };
+    let name = filename.to_string_lossy().to_string();
+    if options.meta {
+        return read_file_meta(&filename, name, x, options);
+    }
+    let bytes = match cache::read(&filename) {
+        Ok(d) if options.bytes || std::str::from_utf8(&d).is_ok() => d.to_vec(),
+        _ => return Ok(Kind::Name(name, x.span())),
+    };
+    path::check_symlinks(&filename, options, x.span())?;
+    check_file(&filename, &bytes, options, x.span())?;
+    let bytes = if options.merges() {
+        merge::apply(&filename, bytes, options, x.span())?
+    } else {
+        bytes
+    };
+    Ok(Kind::Data(
+        Data {
+            bytes,
+            span: x.span(),
+        },
+        name,
+    ))
+}
+
+/// Checks the file at `filename` as it was read, with `bytes`, before any option changes it
+///
+/// This runs for every file read, whichever macro it's read for, including `toml_const!`, so a
+/// file can't avoid the checks by being trimmed or selected from. It's checked against the
+/// deny-list of private key material, for its permissions if it's `secret`, and for its signature
+/// if `minisign_key` is given.
+pub(crate) fn check_file(
+    filename: &std::path::Path,
+    bytes: &[u8],
+    options: &Options,
+    span: Span,
+) -> syn::Result<()> {
+    let description = options.redact(filename.to_string_lossy().to_string());
+    if !options.allow_sensitive {
+        if let Some(reason) = sensitive::check(filename, &String::from_utf8_lossy(bytes)) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "{} looks like private key material, as {}. Add the `allow_sensitive` \
+                     option if embedding it is intended",
+                    description, reason
+                ),
+            ));
+        }
+    }
+    if options.secret {
+        permissions::check(filename, &description, options, span)?;
+    }
+    if let Some(public_key) = &options.minisign_key {
+        signature::verify(
+            bytes,
+            Some(filename),
+            public_key,
+            options.signature_env.as_ref(),
+            &description,
+        )?;
+    }
+    Ok(())
+}
+
+/// The piece of file metadata embedded by `file_meta_env!`
+enum MetaField {
+    Size,
+    Modified,
+}
+
+impl MetaField {
+    /// Parses the `field` option, which is `"mtime"` if not given
+    fn parse(field: Option<&LitStr>) -> syn::Result<MetaField> {
+        let Some(field) = field else {
+            return Ok(MetaField::Modified);
+        };
+        match field.value().as_str() {
+            "size" => Ok(MetaField::Size),
+            "mtime" => Ok(MetaField::Modified),
+            f => Err(syn::Error::new(
+                field.span(),
+                format!("Unknown file metadata field {}, expected size or mtime", f),
+            )),
+        }
+    }
+}
+
+/// Reads the size in bytes or the modification time in seconds since the Unix epoch of the file
+/// at `filename`, named `name`, as text
+fn read_file_meta(
+    filename: &std::path::Path,
+    name: String,
+    x: &LitStr,
+    options: &Options,
+) -> syn::Result<Kind> {
+    let field = MetaField::parse(options.field.as_ref())?;
+    let metadata = match std::fs::metadata(filename) {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return Ok(Kind::Name(name, x.span())),
+    };
+    path::check_symlinks(filename, options, x.span())?;
+    track::path(filename);
+    let value = match field {
+        MetaField::Size => metadata.len(),
+        MetaField::Modified => {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok());
+            match modified {
+                Some(modified) => modified.as_secs(),
+                None => return Ok(Kind::Name(name, x.span())),
+            }
+        }
+    };
+    Ok(Kind::Data(Data::text(&value.to_string(), x.span()), name))
+}
+
+/// Reads the environment variable named by `x`, which must be Unicode unless the value is
+/// embedded as bytes on Unix
+fn read_from_env(x: &LitStr, options: &Options) -> Kind {
+    let env_var_name = options.env_name(&x.value());
+    let Some(value) = track::var_os(&env_var_name) else {
+        return Kind::Name(env_var_name, x.span());
+    };
+    let bytes = match value.into_string() {
+        Ok(s) if options.unescape_newlines || options.unescape_tabs => {
+            unescape(&s, options).into_bytes()
+        }
+        Ok(s) => s.into_bytes(),
+        Err(value) => match raw_bytes(value).filter(|_| options.bytes) {
+            Some(bytes) => bytes,
+            None => return Kind::Name(env_var_name, x.span()),
+        },
+    };
+    Kind::Data(
+        Data {
+            bytes,
+            span: x.span(),
+        },
+        env_var_name,
+    )
+}
+
+/// Returns the bytes of an environment variable which isn't Unicode, which is only possible on
+/// Unix
+#[cfg(unix)]
+fn raw_bytes(value: std::ffi::OsString) -> Option<Vec<u8>> {
+    Some(std::os::unix::ffi::OsStringExt::into_vec(value))
+}
+
+#[cfg(not(unix))]
+fn raw_bytes(_value: std::ffi::OsString) -> Option<Vec<u8>> {
+    None
+}
+
+/// Replaces the escapes in `s` chosen by the `unescape_newlines` and `unescape_tabs` options, for
+/// CI systems which can only set single-line environment variables
+///
+/// `\\` is an escaped backslash, and any other backslash is left alone.
+fn unescape(s: &str, options: &Options) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let (replacement, len) = match rest.as_bytes().get(1) {
+            Some(b'n') if options.unescape_newlines => ("\n", 2),
+            Some(b'r') if options.unescape_newlines && rest[2..].starts_with("\\n") => ("\r\n", 4),
+            Some(b't') if options.unescape_tabs => ("\t", 2),
+            Some(b'\\') => ("\\", 2),
+            _ => ("\\", 1),
+        };
+        out.push_str(replacement);
+        rest = &rest[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Reads `SOURCE_DATE_EPOCH`, which reproducible builds set to the time to use as the build time
+fn read_source_date_epoch(options: &Options) -> syn::Result<Kind> {
+    let name = "SOURCE_DATE_EPOCH".to_string();
+    let span = Span::call_site();
+    let Ok(epoch) = track::var(&name) else {
+        return Ok(Kind::Name(name, span));
+    };
+    let secs = epoch.trim().parse().map_err(|_| {
+        syn::Error::new(
+            span,
+            format!(
+                "SOURCE_DATE_EPOCH must be a number of seconds, not {}",
+                epoch
+            ),
+        )
+    })?;
+    let time = time::format(secs, options.format.as_ref())?;
+    Ok(Kind::Data(Data::text(&time, span), name))
+}
+
+fn read_clock(options: &Options) -> syn::Result<Kind> {
+    let span = Span::call_site();
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    let time = time::format(secs, options.format.as_ref())?;
+    Ok(Kind::Data(
+        Data::text(&time, span),
+        "the current time".to_string(),
+    ))
+}
@@ -0,0 +1,144 @@
+//! Fallback notices printed during compilation
+//!
+//! The verbosity is taken from `FILE_ENV_CONST_LOG`, which is one of `off`, `warn` or `info`. When
+//! it isn't set, notices are `off`, unless the `log` feature is enabled in which case they're at
+//! `info`. Separately, `FILE_ENV_CONST_LOG_FILE` names a file which every resolution is appended
+//! to, one line each, whatever the verbosity.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::Duration;
+
+use proc_macro2::Span;
+
+use crate::{track, Data, Source};
+
+const LOG_VAR: &str = "FILE_ENV_CONST_LOG";
+const LOG_FILE_VAR: &str = "FILE_ENV_CONST_LOG_FILE";
+
+/// How much to print when falling back from one source to the next
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    /// Print nothing
+    Off,
+    /// Only print when falling back to the default
+    Warn,
+    /// Print every fallback
+    Info,
+}
+
+impl Level {
+    /// Reads the level from the environment
+    pub(crate) fn from_env() -> syn::Result<Self> {
+        match track::var(LOG_VAR) {
+            Ok(level) => match level.as_str() {
+                "off" => Ok(Level::Off),
+                "warn" => Ok(Level::Warn),
+                "info" => Ok(Level::Info),
+                _ => Err(syn::Error::new(
+                    Span::call_site(),
+                    format!(
+                        "{} must be one of off, warn or info, but is {}",
+                        LOG_VAR, level
+                    ),
+                )),
+            },
+            Err(_) if cfg!(feature = "log") => Ok(Level::Info),
+            Err(_) => Ok(Level::Off),
+        }
+    }
+
+    /// Prints a notice that a source wasn't found, described by `not_found`, and that `next` will
+    /// be tried
+    pub(crate) fn fallback(self, not_found: &str, next: Source) {
+        let level = match next {
+            Source::Default => Level::Warn,
+            _ => Level::Info,
+        };
+        if self >= level {
+            eprintln!("{}, trying {}", not_found, next.description());
+        }
+    }
+}
+
+/// A source read while resolving a value, for the log file and the audit manifest
+pub(crate) struct Attempt {
+    pub(crate) source: Source,
+    /// The path or variable name of the source, redacted if the value is secret
+    pub(crate) name: String,
+    /// Why the source wasn't used, or `None` if the value came from it
+    pub(crate) missing: Option<String>,
+}
+
+/// Appends a line describing a resolution to the file named by `FILE_ENV_CONST_LOG_FILE`, if it's
+/// set
+///
+/// The line has the crate being built, the call site, every source in `attempts`, the source the
+/// value was taken from or the error in `found`, and how long it took.
+pub(crate) fn write_file(
+    macro_name: &str,
+    attempts: &[Attempt],
+    found: &syn::Result<(Data, Source, Option<String>)>,
+    elapsed: Duration,
+) {
+    let Ok(path) = track::var(LOG_FILE_VAR) else {
+        return;
+    };
+    if path.is_empty() {
+        return;
+    }
+
+    let call_site = proc_macro::Span::call_site();
+    let tried: Vec<String> = attempts
+        .iter()
+        .map(|attempt| match &attempt.missing {
+            Some(missing) => missing.clone(),
+            None => format!(
+                "found the {} {}",
+                attempt.source.description(),
+                attempt.name
+            ),
+        })
+        .collect();
+    let outcome = match found {
+        Ok((_, Source::Default, _)) => "used the default".to_string(),
+        Ok((_, source, name)) => format!(
+            "used the {} {}",
+            source.description(),
+            attempts
+                .last()
+                .filter(|attempt| attempt.missing.is_none())
+                .map_or_else(
+                    || name.clone().unwrap_or_default(),
+                    |attempt| attempt.name.clone()
+                )
+        ),
+        Err(e) => format!("failed: {}", e.to_string().replace('\n', " ")),
+    };
+    let line = format!(
+        "{} {}:{}:{} {}!: {}{} in {:.3}ms\n",
+        std::env::var("CARGO_PKG_NAME").unwrap_or_default(),
+        call_site.file(),
+        call_site.line(),
+        call_site.column(),
+        macro_name,
+        tried
+            .iter()
+            .map(|tried| format!("{}; ", tried))
+            .collect::<String>(),
+        outcome,
+        elapsed.as_secs_f64() * 1000.0
+    );
+
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(line.as_bytes()));
+    if let Err(e) = written {
+        panic!(
+            "Unable to write to log file {} set by {}: {}",
+            path, LOG_FILE_VAR, e
+        );
+    }
+}
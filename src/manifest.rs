@@ -0,0 +1,99 @@
+//! Machine-readable audit manifest of every value the macros embed
+//!
+//! When `FILE_ENV_CONST_MANIFEST` is set at build time, each macro invocation appends one JSON
+//! object per line to the file it names. Relative paths are resolved against the directory the
+//! compiler is run from, which for cargo is the workspace root.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use crate::sha256;
+use crate::track;
+use crate::Source;
+
+const MANIFEST_VAR: &str = "FILE_ENV_CONST_MANIFEST";
+
+/// A single resolution, as recorded in the manifest
+pub(crate) struct Record<'a> {
+    pub(crate) macro_name: &'a str,
+    pub(crate) source: Source,
+    /// The path or environment variable name the value came from, if it wasn't the default
+    pub(crate) name: Option<&'a str>,
+    pub(crate) content: &'a [u8],
+    /// Whether the value is `secret`, in which case its digest isn't recorded, as it would let the
+    /// value be confirmed by guessing
+    pub(crate) secret: bool,
+    /// Whether the value is the same in every build of the same commit
+    pub(crate) hermetic: bool,
+    /// Every source read while resolving the value, as `<kind>:<name>`, so a build script can
+    /// track them
+    pub(crate) looked_up: &'a [String],
+}
+
+/// Appends `record` to the manifest, if one has been requested
+pub(crate) fn append(record: &Record) {
+    let Ok(path) = track::var(MANIFEST_VAR) else {
+        return;
+    };
+    if path.is_empty() {
+        return;
+    }
+
+    let call_site = proc_macro::Span::call_site();
+    let line = format!(
+        "{{\"macro\":{},\"call_site\":{},\"source\":{},\"name\":{},\"sha256\":{},\"hermetic\":{},\
+         \"looked_up\":[{}]}}\n",
+        json_string(record.macro_name),
+        json_string(&format!(
+            "{}:{}:{}",
+            call_site.file(),
+            call_site.line(),
+            call_site.column()
+        )),
+        json_string(record.source.manifest_name()),
+        record.name.map_or_else(|| "null".to_string(), json_string),
+        if record.secret {
+            "null".to_string()
+        } else {
+            json_string(&sha256::hex_digest(record.content))
+        },
+        record.hermetic,
+        record
+            .looked_up
+            .iter()
+            .map(|name| json_string(name))
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| f.write_all(line.as_bytes()));
+    if let Err(e) = written {
+        panic!(
+            "Unable to write to manifest {} set by {}: {}",
+            path, MANIFEST_VAR, e
+        );
+    }
+}
+
+/// Quotes and escapes `s` as a JSON string
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,134 @@
+//! Merging a file with an overlay beside it, for the `merge` and `overlay` options
+//!
+//! Tables in the overlay are merged into the tables with the same name in the file, recursively,
+//! and any other value in the overlay replaces the file's. The merged document is written out
+//! again, so the file's comments and formatting aren't kept. TOML files need the `toml` feature,
+//! and JSON files are always supported.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+
+use crate::args::Options;
+use crate::{cache, json, transform};
+
+/// Merges the overlay for the file at `path` into `contents`, if the overlay exists
+///
+/// The overlay gets the same checks as the file, so a `.local` file dropped beside it can't embed
+/// anything the file itself couldn't.
+pub(crate) fn apply(
+    path: &Path,
+    contents: Vec<u8>,
+    options: &Options,
+    span: Span,
+) -> syn::Result<Vec<u8>> {
+    let overlay = overlay_path(path, options)?;
+    let Ok(overlay_contents) = cache::read(&overlay) else {
+        return Ok(contents);
+    };
+    crate::path::check_symlinks(&overlay, options, span)?;
+    crate::check_file(&overlay, &overlay_contents, options, span)?;
+    let shown = |path: &Path| options.redact(path.display().to_string());
+    let error = |path: &Path, e: String| syn::Error::new(span, format!("{}: {}", shown(path), e));
+    // A parse error can quote the line it's on, so a secret's only says which file is invalid
+    let invalid = |e: String| {
+        if options.secret {
+            "the file isn't valid JSON".to_string()
+        } else {
+            e
+        }
+    };
+    let text = |path: &Path, bytes: &[u8]| -> syn::Result<String> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| {
+            error(
+                path,
+                "the file isn't valid UTF-8, so it can't be merged".into(),
+            )
+        })
+    };
+    let base = text(path, &contents)?;
+    let overlay_text = text(&overlay, &overlay_contents)?;
+
+    let merged = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            let mut base = json::parse(&base).map_err(|e| error(path, invalid(e)))?;
+            let overlay_value =
+                json::parse(&overlay_text).map_err(|e| error(&overlay, invalid(e)))?;
+            base.merge(overlay_value);
+            base.to_compact()
+        }
+        Some("toml") => merge_toml(&base, &overlay_text, options.secret)
+            .map_err(|(in_overlay, e)| error(if in_overlay { &overlay } else { path }, e))?,
+        _ => {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "{} can't be merged, only .toml and .json files can",
+                    shown(path)
+                ),
+            ))
+        }
+    };
+    Ok(merged.into_bytes())
+}
+
+/// Returns true if the file at `path` has an overlay to merge
+pub(crate) fn overlay_exists(path: &Path, options: &Options) -> bool {
+    overlay_path(path, options).is_ok_and(|overlay| overlay.is_file())
+}
+
+/// Returns the overlay for the file at `path`: the `overlay` option relative to the file's
+/// directory, or `config.local.toml` for `config.toml`
+fn overlay_path(path: &Path, options: &Options) -> syn::Result<PathBuf> {
+    if let Some(overlay) = &options.overlay {
+        let name = transform::template(&overlay.value())
+            .map_err(|e| syn::Error::new(overlay.span(), e))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        return Ok(crate::path::resolve(dir, &name));
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.local.{}", stem, extension.to_string_lossy()),
+        None => format!("{}.local", stem),
+    };
+    Ok(path.with_file_name(name))
+}
+
+/// Merges the TOML document `overlay` into `base`, returning which document an error is in
+///
+/// If `secret` is set, a parse error doesn't quote the document.
+#[cfg(feature = "toml")]
+fn merge_toml(base: &str, overlay: &str, secret: bool) -> Result<String, (bool, String)> {
+    fn merge(base: &mut toml::Table, overlay: toml::Table) {
+        for (key, value) in overlay {
+            match (base.get_mut(&key), value) {
+                (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                    merge(base, overlay)
+                }
+                (_, value) => {
+                    base.insert(key, value);
+                }
+            }
+        }
+    }
+
+    let describe = |e: toml::de::Error| {
+        if secret {
+            "the file isn't valid TOML".to_string()
+        } else {
+            e.to_string()
+        }
+    };
+    let mut base: toml::Table = base.parse().map_err(|e| (false, describe(e)))?;
+    let overlay: toml::Table = overlay.parse().map_err(|e| (true, describe(e)))?;
+    merge(&mut base, overlay);
+    Ok(base.to_string())
+}
+
+#[cfg(not(feature = "toml"))]
+fn merge_toml(_base: &str, _overlay: &str, _secret: bool) -> Result<String, (bool, String)> {
+    Err((
+        false,
+        "merging TOML files requires the `toml` feature".to_string(),
+    ))
+}
@@ -0,0 +1,173 @@
+//! Defining constants describing a value next to it, for `#[metadata]`
+//!
+//! The attribute resolves the macro in the constant's initializer itself, rather than leaving it
+//! to be expanded later, so that the value is known while the sibling constants are generated.
+
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{Attribute, Ident, Macro, Token, Type, Visibility};
+
+use crate::args::without_placeholder;
+use crate::{resolve, resolve_bytes, Resolution, Resolved, Source};
+
+/// A constant whose initializer is one of the value macros
+struct Item {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    ty: Type,
+    mac: Macro,
+}
+
+impl Parse for Item {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![const]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let mac = input.parse().map_err(|e| {
+            syn::Error::new(
+                e.span(),
+                "#[metadata] needs the constant to be initialized by file_env!, env_file!, \
+                 file_env_bytes! or env_file_bytes!",
+            )
+        })?;
+        input.parse::<Token![;]>()?;
+        Ok(Item {
+            attrs,
+            vis,
+            name,
+            ty,
+            mac,
+        })
+    }
+}
+
+/// Expands the constant `item`, followed by `<NAME>_LEN`, `<NAME>_LINES` and `<NAME>_IS_DEFAULT`
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    if let Some(token) = attr.into_iter().next() {
+        return Err(syn::Error::new_spanned(
+            token,
+            "#[metadata] doesn't take arguments",
+        ));
+    }
+    let item: Item = syn::parse2(item)?;
+    match resolve_macro(&item.mac)? {
+        Resolution::Placeholder(placeholder, _) => {
+            let debug = constants(
+                &item,
+                &Resolution::Value(placeholder),
+                quote!(#[cfg(debug_assertions)]),
+            )?;
+            let Item {
+                attrs,
+                vis,
+                name,
+                ty,
+                mac,
+            } = item;
+            let mac = Macro {
+                tokens: without_placeholder(mac.tokens),
+                ..mac
+            };
+            Ok(quote! {
+                #debug
+                #[cfg(not(debug_assertions))]
+                #[::file_env_const::metadata]
+                #(#attrs)*
+                #vis const #name: #ty = #mac;
+            })
+        }
+        resolution => constants(&item, &resolution, TokenStream::new()),
+    }
+}
+
+/// Returns the constant `item` with the value of `resolution` and the constants describing it,
+/// each behind the attribute `cfg`
+fn constants(item: &Item, resolution: &Resolution, cfg: TokenStream) -> syn::Result<TokenStream> {
+    let Item {
+        attrs,
+        vis,
+        name,
+        ty,
+        mac,
+    } = item;
+    let value = resolution.embed(Resolved::value)?;
+    let len = resolution.embed(|resolved| {
+        let len = Literal::usize_unsuffixed(known(resolved, mac)?.len());
+        Ok(quote!(#len))
+    })?;
+    let lines = resolution.embed(|resolved| {
+        let lines = String::from_utf8_lossy(known(resolved, mac)?)
+            .lines()
+            .count();
+        let lines = Literal::usize_unsuffixed(lines);
+        Ok(quote!(#lines))
+    })?;
+    let is_default = resolution.embed(|resolved| {
+        let is_default = resolved.source == Source::Default;
+        Ok(quote!(#is_default))
+    })?;
+
+    let len_name = format_ident!("{}_LEN", name);
+    let lines_name = format_ident!("{}_LINES", name);
+    let is_default_name = format_ident!("{}_IS_DEFAULT", name);
+    let len_doc = format!("The length of [`{}`] in bytes", name);
+    let lines_doc = format!("The number of lines in [`{}`]", name);
+    let is_default_doc = format!("Whether [`{}`] is the macro's default", name);
+    Ok(quote! {
+        #(#attrs)*
+        #cfg
+        #vis const #name: #ty = #value;
+        #[doc = #len_doc]
+        #cfg
+        #vis const #len_name: usize = #len;
+        #[doc = #lines_doc]
+        #cfg
+        #vis const #lines_name: usize = #lines;
+        #[doc = #is_default_doc]
+        #cfg
+        #vis const #is_default_name: bool = #is_default;
+    })
+}
+
+/// Resolves the invocation `mac` of one of the value macros
+fn resolve_macro(mac: &Macro) -> syn::Result<Resolution> {
+    let name = mac
+        .path
+        .segments
+        .last()
+        .map(|segment| segment.ident.to_string());
+    let input = mac.tokens.clone().into();
+    match name.as_deref() {
+        Some("file_env") => resolve("file_env", input, &[Source::File, Source::Env]),
+        Some("env_file") => resolve("env_file", input, &[Source::Env, Source::File]),
+        Some("file_env_bytes") => {
+            resolve_bytes("file_env_bytes", input, &[Source::File, Source::Env])
+        }
+        Some("env_file_bytes") => {
+            resolve_bytes("env_file_bytes", input, &[Source::Env, Source::File])
+        }
+        _ => Err(syn::Error::new_spanned(
+            &mac.path,
+            "#[metadata] works with file_env!, env_file!, file_env_bytes! and env_file_bytes!",
+        )),
+    }
+}
+
+/// Returns the bytes of `resolved`, which aren't known while expanding if the default is an
+/// expression
+fn known<'a>(resolved: &'a Resolved, mac: &Macro) -> syn::Result<&'a [u8]> {
+    match resolved.expression {
+        Some(_) => Err(syn::Error::new_spanned(
+            &mac.path,
+            "#[metadata] can't describe a default expression, as its value isn't known until \
+             it's compiled",
+        )),
+        None => Ok(&resolved.data.bytes),
+    }
+}
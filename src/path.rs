@@ -0,0 +1,122 @@
+//! Resolving the filenames given to the macros, including searching parent directories for the
+//! `search_up` option, and checking them against the `follow_symlinks` policy
+//!
+//! Filenames can use either `/` or `\` as the separator on every platform, so the same invocation
+//! works on Windows and Unix build agents. On Windows, extended-length `\\?\` paths are accepted
+//! and resolved like the ordinary path they name.
+
+use std::path::{Component, Path, PathBuf};
+
+use proc_macro2::Span;
+
+use crate::args::Options;
+use crate::config;
+
+/// Resolves the filename `name` against the directory `base`, unless it's absolute
+pub(crate) fn resolve(base: &Path, name: &str) -> PathBuf {
+    let name = strip_verbatim(name);
+    let name = if cfg!(windows) {
+        name.replace('/', "\\")
+    } else {
+        name.replace('\\', "/")
+    };
+    normalize(&base.join(name))
+}
+
+/// Resolves the relative filename `name` against `base` and each of its parents up to the
+/// workspace root, returning the first which exists, or the path in `base` if none of them do
+///
+/// This is how `.env` files are usually discovered, so the members of a workspace can share a
+/// file without `../` paths. Absolute filenames are used as they are.
+pub(crate) fn search_up(base: &Path, name: &str) -> PathBuf {
+    let nearest = resolve(base, name);
+    if Path::new(&strip_verbatim(name)).is_absolute() {
+        return nearest;
+    }
+    let top = normalize(&config::workspace_root());
+    let base = normalize(base);
+    base.ancestors()
+        .take_while(|dir| dir.starts_with(&top))
+        .map(|dir| resolve(dir, name))
+        .find(|path| path.is_file())
+        .unwrap_or(nearest)
+}
+
+/// Turns an extended-length path such as `\\?\C:\dir` or `\\?\UNC\server\share` into the
+/// ordinary path it names, so it's normalized in the same way
+fn strip_verbatim(name: &str) -> String {
+    match name.strip_prefix(r"\\?\") {
+        Some(rest) => match rest.strip_prefix(r"UNC\") {
+            Some(unc) => format!(r"\\{}", unc),
+            None => rest.to_string(),
+        },
+        None => name.to_string(),
+    }
+}
+
+/// Removes `.` components from `path`, and `..` components along with the component before them,
+/// without touching the filesystem
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push(component);
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Checks that the file at `path`, which exists, can be read under the `follow_symlinks` option
+///
+/// When symlinks aren't followed the file must be inside the root, and neither it nor any directory
+/// between the root and it can be a symlink. The canonical file must be inside the canonical root
+/// too, in case the file system resolves the path differently from the way it's written.
+pub(crate) fn check_symlinks(path: &Path, options: &Options, span: Span) -> syn::Result<()> {
+    if options.follow_symlinks.as_ref().is_none_or(|f| f.value) {
+        return Ok(());
+    }
+    let root = normalize(&options.root);
+    let path = normalize(path);
+    let shown = |path: &Path| options.redact(path.display().to_string());
+    let outside = || {
+        syn::Error::new(
+            span,
+            format!(
+                "{} is outside {}, which `follow_symlinks = false` forbids",
+                shown(&path),
+                shown(&root)
+            ),
+        )
+    };
+    let Ok(inside) = path.strip_prefix(&root) else {
+        return Err(outside());
+    };
+    let mut current = root.clone();
+    for component in inside.components() {
+        current.push(component);
+        let is_symlink = std::fs::symlink_metadata(&current)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink());
+        if is_symlink {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "{} is reached through the symlink {}, which `follow_symlinks = false` forbids",
+                    shown(&path),
+                    shown(&current)
+                ),
+            ));
+        }
+    }
+    let canonical_root = std::fs::canonicalize(&root).map_err(|_| outside())?;
+    let canonical = std::fs::canonicalize(&path).map_err(|_| outside())?;
+    if !canonical.starts_with(canonical_root) {
+        return Err(outside());
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+//! Checking that files embedded with the `secret` option are protected, on Unix
+//!
+//! A secret file which every user can read, or which is owned by a user other than the one running
+//! the build, is reported according to `secret_permissions` in `file_env_const.toml`: a warning by
+//! default, or a compile error.
+
+use std::path::Path;
+
+use proc_macro2::Span;
+
+use crate::args::Options;
+
+/// What to do when a secret file isn't protected
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Policy {
+    Off,
+    /// Print a warning during compilation
+    #[default]
+    Warn,
+    /// Fail the build
+    Error,
+}
+
+impl Policy {
+    /// Parses the `secret_permissions` setting
+    pub(crate) fn parse(s: &str) -> Result<Policy, String> {
+        match s {
+            "off" => Ok(Policy::Off),
+            "warn" => Ok(Policy::Warn),
+            "error" => Ok(Policy::Error),
+            _ => Err(format!(
+                "`secret_permissions` must be one of off, warn or error, not {}",
+                s
+            )),
+        }
+    }
+}
+
+/// Reports the secret file at `path`, described by `description`, if it isn't protected
+pub(crate) fn check(
+    path: &Path,
+    description: &str,
+    options: &Options,
+    span: Span,
+) -> syn::Result<()> {
+    if options.secret_permissions == Policy::Off {
+        return Ok(());
+    }
+    let Some(problem) = problem(path) else {
+        return Ok(());
+    };
+    let message = format!("The secret file {} {}", description, problem);
+    if options.secret_permissions == Policy::Error {
+        return Err(syn::Error::new(span, message));
+    }
+    eprintln!("warning: {}", message);
+    Ok(())
+}
+
+/// Describes why the file at `path` isn't protected, if it isn't
+#[cfg(unix)]
+fn problem(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path).ok()?;
+    let mode = metadata.mode() & 0o777;
+    if mode & 0o004 != 0 {
+        return Some(format!("is readable by every user, with mode {:o}", mode));
+    }
+    // SAFETY: geteuid has no preconditions and can't fail
+    let user = unsafe { libc::geteuid() };
+    if metadata.uid() != user {
+        return Some(format!(
+            "is owned by user {}, not the user running the build",
+            metadata.uid()
+        ));
+    }
+    None
+}
+
+#[cfg(not(unix))]
+fn problem(_path: &Path) -> Option<String> {
+    None
+}
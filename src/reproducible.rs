@@ -0,0 +1,92 @@
+//! Reporting values which make the build non-reproducible, for `FILE_ENV_CONST_REPRODUCIBLE`
+//!
+//! A value taken from an environment variable, URL, command or the current time can differ
+//! between two builds of the same commit. Files in the workspace, git metadata, `SOURCE_DATE_EPOCH`
+//! and defaults are treated as hermetic, as they come from the checked-in source or are set for
+//! reproducibility. Files outside the workspace, such as `/etc/os-release`, and files with an
+//! overlay merged into them, which is usually ignored by git, aren't.
+
+use std::path::Path;
+
+use proc_macro2::Span;
+
+use crate::args::Options;
+use crate::{config, merge, path, track, Source};
+
+const REPRODUCIBLE_VAR: &str = "FILE_ENV_CONST_REPRODUCIBLE";
+
+/// What to do when a value comes from a source which isn't hermetic
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Off,
+    /// Print a warning during compilation
+    Warn,
+    /// Fail the build
+    Error,
+}
+
+impl Mode {
+    /// Reads the mode from the environment
+    pub(crate) fn from_env() -> syn::Result<Self> {
+        match track::var(REPRODUCIBLE_VAR) {
+            Ok(mode) => match mode.as_str() {
+                "off" => Ok(Mode::Off),
+                "warn" => Ok(Mode::Warn),
+                "error" => Ok(Mode::Error),
+                _ => Err(syn::Error::new(
+                    Span::call_site(),
+                    format!(
+                        "{} must be one of off, warn or error, but is {}",
+                        REPRODUCIBLE_VAR, mode
+                    ),
+                )),
+            },
+            Err(_) => Ok(Mode::Off),
+        }
+    }
+
+    /// Warns or fails for a value described by `description`, if [`non_hermetic`] gave a
+    /// `reason` it isn't hermetic
+    pub(crate) fn check(
+        self,
+        reason: Option<&str>,
+        description: &str,
+        span: Span,
+    ) -> syn::Result<()> {
+        let Some(reason) = reason.filter(|_| self != Mode::Off) else {
+            return Ok(());
+        };
+        let message = format!("{} is a non-hermetic input, as {}", description, reason);
+        if self == Mode::Error {
+            return Err(syn::Error::new(
+                span,
+                format!("{}, and {} forbids this", message, REPRODUCIBLE_VAR),
+            ));
+        }
+        eprintln!("warning: {}", message);
+        Ok(())
+    }
+}
+
+/// Returns why a value read from `source`, named `name`, can differ between two builds of the
+/// same commit, or `None` if it can't
+pub(crate) fn non_hermetic(
+    source: Source,
+    name: Option<&str>,
+    options: &Options,
+) -> Option<String> {
+    if let (Source::File, Some(name)) = (source, name) {
+        let file = path::normalize(Path::new(name));
+        if !file.starts_with(path::normalize(&config::workspace_root())) {
+            return Some("it was read from a file outside the workspace".to_string());
+        }
+        if options.merges() && merge::overlay_exists(&file, options) {
+            return Some("an overlay was merged into the file it was read from".to_string());
+        }
+    }
+    if source.is_hermetic() {
+        None
+    } else {
+        Some(format!("it was read from the {}", source.description()))
+    }
+}
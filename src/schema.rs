@@ -0,0 +1,358 @@
+//! Validating JSON values against a JSON Schema, for the `schema` option with the `schema` feature
+//!
+//! This supports the keywords used to describe configuration files: `type`, `enum`, `const`,
+//! `properties`, `required`, `additionalProperties`, `items`, `minItems`, `maxItems`,
+//! `minLength`, `maxLength`, `minimum`, `maximum`, `allOf`, `anyOf` and `oneOf`, along with
+//! annotations such as `title` and `description`. A schema using any other keyword, such as `$ref`
+//! or `pattern`, is an error rather than being checked only in part.
+
+use proc_macro2::Span;
+use syn::LitStr;
+
+use crate::args::Options;
+#[cfg(feature = "schema")]
+use crate::json::Value;
+
+/// Checks that `value` is JSON which matches the schema in the file named by `schema`
+#[cfg(feature = "schema")]
+pub(crate) fn check(
+    value: &[u8],
+    schema: &LitStr,
+    options: &Options,
+    span: Span,
+) -> syn::Result<()> {
+    let path = crate::path::resolve(&options.base_dir, &schema.value());
+    let schema_error = |e: String| {
+        syn::Error::new(
+            schema.span(),
+            format!("The schema {} {}", path.display(), e),
+        )
+    };
+    let contents =
+        crate::cache::read(&path).map_err(|e| schema_error(format!("can't be read: {}", e)))?;
+    let contents = std::str::from_utf8(&contents)
+        .map_err(|_| schema_error("isn't valid UTF-8".to_string()))?;
+    let schema_value =
+        crate::json::parse(contents).map_err(|e| schema_error(format!("is invalid: {}", e)))?;
+    check_keywords(&schema_value, "$").map_err(schema_error)?;
+
+    let value = std::str::from_utf8(value)
+        .map_err(|_| syn::Error::new(span, "The value isn't valid UTF-8, so it can't be JSON"))?;
+    let value = crate::json::parse(value).map_err(|e| syn::Error::new(span, e))?;
+    validate(&value, &schema_value, "$", options.secret).map_err(|e| {
+        syn::Error::new(
+            span,
+            format!(
+                "The value doesn't match the schema {}: {}",
+                path.display(),
+                e
+            ),
+        )
+    })
+}
+
+#[cfg(not(feature = "schema"))]
+pub(crate) fn check(
+    _value: &[u8],
+    schema: &LitStr,
+    _options: &Options,
+    _span: Span,
+) -> syn::Result<()> {
+    Err(syn::Error::new(
+        schema.span(),
+        "The `schema` option requires the `schema` feature",
+    ))
+}
+
+/// The keywords which are checked, or which are annotations and don't affect validation
+#[cfg(feature = "schema")]
+const KEYWORDS: &[&str] = &[
+    "type",
+    "enum",
+    "const",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "minItems",
+    "maxItems",
+    "minLength",
+    "maxLength",
+    "minimum",
+    "maximum",
+    "allOf",
+    "anyOf",
+    "oneOf",
+    "$schema",
+    "$id",
+    "$comment",
+    "title",
+    "description",
+    "default",
+    "examples",
+];
+
+/// Checks that `schema`, found at the JSON path `path` in the schema file, and the schemas in it
+/// only use supported keywords
+#[cfg(feature = "schema")]
+fn check_keywords(schema: &Value, path: &str) -> Result<(), String> {
+    let Value::Object(members) = schema else {
+        return Ok(());
+    };
+    for (key, member) in members {
+        if !KEYWORDS.contains(&key.as_str()) {
+            return Err(format!(
+                "uses `{}` at {}, which isn't supported, so the value can't be fully checked",
+                key, path
+            ));
+        }
+        let member_path = format!("{}.{}", path, key);
+        match (key.as_str(), member) {
+            ("properties", Value::Object(properties)) => {
+                for (name, property) in properties {
+                    check_keywords(property, &format!("{}.{}", member_path, name))?;
+                }
+            }
+            ("items" | "additionalProperties", _) => check_keywords(member, &member_path)?,
+            ("allOf" | "anyOf" | "oneOf", Value::Array(schemas)) => {
+                for (i, schema) in schemas.iter().enumerate() {
+                    check_keywords(schema, &format!("{}[{}]", member_path, i))?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns the member `key` of the object `schema`, if it's an object with that member
+#[cfg(feature = "schema")]
+fn keyword<'a>(schema: &'a Value, key: &str) -> Option<&'a Value> {
+    match schema {
+        Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+/// Returns the number `value` as an `f64`, if it's a number
+#[cfg(feature = "schema")]
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Returns the JSON Schema type names which `value` has
+#[cfg(feature = "schema")]
+fn type_names(value: &Value) -> &'static [&'static str] {
+    match value {
+        Value::Null => &["null"],
+        Value::Bool(_) => &["boolean"],
+        Value::Number(_) if number(value).is_some_and(|n| n.fract() == 0.0) => {
+            &["number", "integer"]
+        }
+        Value::Number(_) => &["number"],
+        Value::String(_) => &["string"],
+        Value::Array(_) => &["array"],
+        Value::Object(_) => &["object"],
+    }
+}
+
+/// Returns true if `a` and `b` are the same JSON value, comparing numbers by value
+#[cfg(feature = "schema")]
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Null, Value::Null) => true,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Number(_), Value::Number(_)) => number(a) == number(b),
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter().all(|(key, a)| {
+                    b.iter()
+                        .find(|(k, _)| k == key)
+                        .is_some_and(|(_, b)| equal(a, b))
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Checks `value`, found at the JSON path `path`, against `schema`, describing the first
+/// violation
+///
+/// If `secret` is set, the description doesn't quote any part of `value`.
+#[cfg(feature = "schema")]
+fn validate(value: &Value, schema: &Value, path: &str, secret: bool) -> Result<(), String> {
+    let fail = |message: String| Err(format!("{}: {}", path, message));
+    match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return fail("no value is allowed here".to_string()),
+        Value::Object(_) => {}
+        _ => return fail("the schema here isn't an object".to_string()),
+    }
+
+    match keyword(schema, "type") {
+        Some(Value::String(expected)) if !type_names(value).contains(&expected.as_str()) => {
+            return fail(format!(
+                "expected {}, found {}",
+                expected,
+                type_names(value)[0]
+            ));
+        }
+        Some(Value::Array(expected)) => {
+            let names: Vec<&str> = expected
+                .iter()
+                .filter_map(|t| match t {
+                    Value::String(t) => Some(t.as_str()),
+                    _ => None,
+                })
+                .collect();
+            if !type_names(value).iter().any(|t| names.contains(t)) {
+                return fail(format!(
+                    "expected {}, found {}",
+                    names.join(" or "),
+                    type_names(value)[0]
+                ));
+            }
+        }
+        _ => {}
+    }
+    if let Some(Value::Array(allowed)) = keyword(schema, "enum") {
+        if !allowed.iter().any(|allowed| equal(value, allowed)) {
+            return fail(format!(
+                "expected one of {}",
+                allowed
+                    .iter()
+                    .map(Value::to_compact)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+    if let Some(expected) = keyword(schema, "const") {
+        if !equal(value, expected) {
+            return fail(format!("expected {}", expected.to_compact()));
+        }
+    }
+
+    if let Some(n) = number(value) {
+        let shown = if secret {
+            "the number".to_string()
+        } else {
+            n.to_string()
+        };
+        if let Some(minimum) = keyword(schema, "minimum").and_then(number) {
+            if n < minimum {
+                return fail(format!("{} is less than the minimum of {}", shown, minimum));
+            }
+        }
+        if let Some(maximum) = keyword(schema, "maximum").and_then(number) {
+            if n > maximum {
+                return fail(format!("{} is more than the maximum of {}", shown, maximum));
+            }
+        }
+    }
+    if let Value::String(s) = value {
+        let len = s.chars().count() as f64;
+        if let Some(min) = keyword(schema, "minLength").and_then(number) {
+            if len < min {
+                return fail(format!("the string is shorter than {} characters", min));
+            }
+        }
+        if let Some(max) = keyword(schema, "maxLength").and_then(number) {
+            if len > max {
+                return fail(format!("the string is longer than {} characters", max));
+            }
+        }
+    }
+
+    if let Value::Array(items) = value {
+        let len = items.len() as f64;
+        if let Some(min) = keyword(schema, "minItems").and_then(number) {
+            if len < min {
+                return fail(format!("the array has fewer than {} items", min));
+            }
+        }
+        if let Some(max) = keyword(schema, "maxItems").and_then(number) {
+            if len > max {
+                return fail(format!("the array has more than {} items", max));
+            }
+        }
+        if let Some(item_schema) = keyword(schema, "items") {
+            for (i, item) in items.iter().enumerate() {
+                validate(item, item_schema, &format!("{}[{}]", path, i), secret)?;
+            }
+        }
+    }
+
+    if let Value::Object(members) = value {
+        if let Some(Value::Array(required)) = keyword(schema, "required") {
+            for key in required {
+                if let Value::String(key) = key {
+                    if !members.iter().any(|(k, _)| k == key) {
+                        return fail(format!("the required property {} is missing", key));
+                    }
+                }
+            }
+        }
+        let properties = keyword(schema, "properties");
+        for (key, member) in members {
+            let member_schema = properties.and_then(|p| keyword(p, key));
+            // A property the schema names is shown, but any other key comes from the value
+            let shown = if secret && member_schema.is_none() {
+                "<key>"
+            } else {
+                key.as_str()
+            };
+            let member_path = format!("{}.{}", path, shown);
+            match member_schema {
+                Some(member_schema) => validate(member, member_schema, &member_path, secret)?,
+                None => {
+                    if let Some(additional) = keyword(schema, "additionalProperties") {
+                        if let Value::Bool(false) = additional {
+                            return fail(if secret {
+                                "a property the schema doesn't name isn't allowed".to_string()
+                            } else {
+                                format!("the property {} isn't allowed", key)
+                            });
+                        }
+                        validate(member, additional, &member_path, secret)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Array(schemas)) = keyword(schema, "allOf") {
+        for schema in schemas {
+            validate(value, schema, path, secret)?;
+        }
+    }
+    if let Some(Value::Array(schemas)) = keyword(schema, "anyOf") {
+        let errors: Vec<String> = schemas
+            .iter()
+            .filter_map(|schema| validate(value, schema, path, secret).err())
+            .collect();
+        if errors.len() == schemas.len() {
+            return fail(format!("matches none of anyOf: {}", errors.join("; ")));
+        }
+    }
+    if let Some(Value::Array(schemas)) = keyword(schema, "oneOf") {
+        let matches = schemas
+            .iter()
+            .filter(|schema| validate(value, schema, path, secret).is_ok())
+            .count();
+        if matches != 1 {
+            return fail(format!(
+                "matches {} of the oneOf schemas, rather than exactly one",
+                matches
+            ));
+        }
+    }
+    Ok(())
+}
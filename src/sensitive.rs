@@ -0,0 +1,36 @@
+//! Heuristics for spotting private key material, so that it isn't embedded by accident
+
+use std::path::Path;
+
+/// File names used by `ssh-keygen` for private keys
+const KEY_FILE_NAMES: [&str; 6] = [
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ecdsa_sk",
+    "id_ed25519",
+    "id_ed25519_sk",
+];
+
+/// Returns why the file at `path` with `content` looks like a private key, if it does
+pub(crate) fn check(path: &Path, content: &str) -> Option<&'static str> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if KEY_FILE_NAMES.contains(&file_name) {
+        return Some("it has the name of an SSH private key");
+    }
+    if path.components().any(|c| c.as_os_str() == ".ssh") && !file_name.ends_with(".pub") {
+        return Some("it is in an .ssh directory");
+    }
+    // Anywhere in a line, so a key quoted in a TOML or JSON credentials file is found too
+    let pem = content.lines().any(|l| {
+        l.find("-----BEGIN ")
+            .is_some_and(|start| l[start..].contains("PRIVATE KEY-----"))
+    });
+    if pem {
+        return Some("it contains a PEM private key");
+    }
+    if content.starts_with("PuTTY-User-Key-File-") {
+        return Some("it is a PuTTY private key");
+    }
+    None
+}
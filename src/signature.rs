@@ -0,0 +1,90 @@
+//! Verification of minisign signatures over embedded values, with the `signature` feature
+//!
+//! The signature is read from the environment variable given by `signature_env`, or otherwise
+//! from a `.sig` file next to the file the value was read from.
+
+use std::path::Path;
+
+use syn::LitStr;
+
+#[cfg(feature = "signature")]
+use crate::{cache, track};
+
+/// Checks that `content` is signed by the minisign `public_key`
+///
+/// `path` is the file the content was read from, if any, and `name` describes the source in
+/// errors. For a file, `content` is its bytes as read, before any option like `trim` changes
+/// them.
+#[cfg(feature = "signature")]
+pub(crate) fn verify(
+    content: &[u8],
+    path: Option<&Path>,
+    public_key: &LitStr,
+    signature_env: Option<&LitStr>,
+    name: &str,
+) -> syn::Result<()> {
+    use minisign_verify::{PublicKey, Signature};
+
+    let key = PublicKey::from_base64(&public_key.value()).map_err(|e| {
+        syn::Error::new(
+            public_key.span(),
+            format!("Invalid minisign public key: {}", e),
+        )
+    })?;
+
+    let signature = match (signature_env, path) {
+        (Some(var), _) => track::var(&var.value()).map_err(|_| {
+            syn::Error::new(
+                var.span(),
+                format!("No signature for {} found in {}", name, var.value()),
+            )
+        })?,
+        (None, Some(path)) => {
+            let mut sig_path = path.as_os_str().to_owned();
+            sig_path.push(".sig");
+            let bytes = cache::read(Path::new(&sig_path)).map_err(|_| {
+                syn::Error::new(
+                    public_key.span(),
+                    format!("No signature for {} found in a .sig file next to it", name),
+                )
+            })?;
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        (None, None) => {
+            return Err(syn::Error::new(
+                public_key.span(),
+                format!(
+                    "No signature for {}, give one with `signature_env = \"VAR\"`",
+                    name
+                ),
+            ))
+        }
+    };
+
+    let signature = Signature::decode(&signature).map_err(|e| {
+        syn::Error::new(
+            public_key.span(),
+            format!("Invalid minisign signature for {}: {}", name, e),
+        )
+    })?;
+    key.verify(content, &signature, false).map_err(|e| {
+        syn::Error::new(
+            public_key.span(),
+            format!("The signature of {} doesn't verify: {}", name, e),
+        )
+    })
+}
+
+#[cfg(not(feature = "signature"))]
+pub(crate) fn verify(
+    _content: &[u8],
+    _path: Option<&Path>,
+    public_key: &LitStr,
+    _signature_env: Option<&LitStr>,
+    _name: &str,
+) -> syn::Result<()> {
+    Err(syn::Error::new(
+        public_key.span(),
+        "Verifying signatures requires the `signature` feature",
+    ))
+}
@@ -0,0 +1,212 @@
+//! Splitting a value into several constants by a pattern, for `file_env_split!`
+//!
+//! A pattern like `"HOST:PORT"` is a sequence of fields, each an upper case identifier, and the
+//! literal text between them. Each field takes the value up to the next occurrence of the text
+//! after it, and the last field takes the rest.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::{LitStr, Token, Visibility};
+
+use crate::args::Args;
+use crate::{resolve_branches, Data, Resolution, Resolved, Source};
+
+/// A `file_env_split!` invocation, `vis "PATTERN" <- args`
+struct Split {
+    vis: Visibility,
+    pattern: LitStr,
+    args: Args,
+}
+
+impl Parse for Split {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        let pattern = input.parse().map_err(|e| {
+            syn::Error::new(
+                e.span(),
+                "file_env_split! starts with the pattern, try \
+                 file_env_split!(\"HOST:PORT\" <- \"addr.txt\", \"ADDR\")",
+            )
+        })?;
+        input.parse::<Token![<-]>()?;
+        let args = input.parse()?;
+        Ok(Split { vis, pattern, args })
+    }
+}
+
+/// A part of a pattern
+#[derive(PartialEq)]
+enum Piece {
+    /// A field, which is the name of its constant
+    Field(String),
+    /// Text which must appear in the value
+    Text(String),
+}
+
+/// Expands to a `&'static str` constant for each field of the pattern in `input`
+pub(crate) fn expand(input: proc_macro::TokenStream) -> syn::Result<TokenStream> {
+    let tokens = TokenStream::from(input.clone());
+    let Split {
+        vis,
+        pattern,
+        mut args,
+    } = syn::parse(input)?;
+    args.tokens = tokens;
+    let pieces = parse_pattern(&pattern)?;
+    if let Some(option) = args.options.embeds_non_str() {
+        return Err(syn::Error::new(
+            pattern.span(),
+            format!(
+                "`{}` can't be used with file_env_split!, as its constants are `&'static str`",
+                option
+            ),
+        ));
+    }
+    let (resolution, cfg, invocation) =
+        match resolve_branches("file_env_split", args, &[Source::File, Source::Env])? {
+            Resolution::Placeholder(placeholder, invocation) => (
+                Resolution::Value(placeholder),
+                quote!(#[cfg(debug_assertions)]),
+                quote!(#[cfg(not(debug_assertions))] #invocation),
+            ),
+            resolution => (resolution, TokenStream::new(), TokenStream::new()),
+        };
+
+    let mut consts = Vec::new();
+    for (i, piece) in pieces.iter().enumerate() {
+        let Piece::Field(field) = piece else {
+            continue;
+        };
+        let name = format_ident!("{}", field, span = pattern.span());
+        let value = resolution.embed(|resolved| {
+            let parts = split(resolved, &pieces, &pattern)?;
+            let part = Resolved {
+                data: Data::text(parts[fields_before(&pieces, i)], resolved.data.span),
+                source: resolved.source,
+                path: None,
+                expression: None,
+                options: resolved.options.clone(),
+            };
+            part.value()
+        })?;
+        consts.push(quote!(#cfg #vis const #name: &'static str = #value;));
+    }
+    Ok(quote!(#(#consts)* #invocation))
+}
+
+/// Splits `pattern` into its fields and text, checking that it defines at least one constant
+fn parse_pattern(pattern: &LitStr) -> syn::Result<Vec<Piece>> {
+    let value = pattern.value();
+    let mut pieces = Vec::new();
+    let mut rest = value.as_str();
+    let starts_field = |c: char| c.is_ascii_uppercase() || c == '_';
+    while let Some(c) = rest.chars().next() {
+        let len = if starts_field(c) {
+            rest.find(|c: char| !starts_field(c) && !c.is_ascii_digit())
+        } else {
+            rest.find(starts_field)
+        };
+        let (piece, after) = rest.split_at(len.unwrap_or(rest.len()));
+        if starts_field(c) {
+            if pieces.contains(&Piece::Field(piece.to_string())) {
+                return Err(syn::Error::new(
+                    pattern.span(),
+                    format!("{} appears more than once in the pattern", piece),
+                ));
+            }
+            pieces.push(Piece::Field(piece.to_string()));
+        } else {
+            pieces.push(Piece::Text(piece.to_string()));
+        }
+        rest = after;
+    }
+    if !pieces.iter().any(|piece| matches!(piece, Piece::Field(_))) {
+        return Err(syn::Error::new(
+            pattern.span(),
+            "The pattern has no fields, name each constant in upper case, as in \"HOST:PORT\"",
+        ));
+    }
+    Ok(pieces)
+}
+
+/// Returns the number of fields before the piece at `index`
+fn fields_before(pieces: &[Piece], index: usize) -> usize {
+    pieces[..index]
+        .iter()
+        .filter(|piece| matches!(piece, Piece::Field(_)))
+        .count()
+}
+
+/// Returns the part of the value of `resolved` for each field of `pieces`, or an error if the
+/// value doesn't have the shape of the pattern
+fn split<'a>(
+    resolved: &'a Resolved,
+    pieces: &[Piece],
+    pattern: &LitStr,
+) -> syn::Result<Vec<&'a str>> {
+    if let Some(expression) = &resolved.expression {
+        return Err(syn::Error::new_spanned(
+            expression,
+            "file_env_split! needs a default string to split, not an expression",
+        ));
+    }
+    let value = resolved.data.as_str()?;
+    let mismatch = |reason: String| {
+        let value = if resolved.options.secret {
+            "The value".to_string()
+        } else {
+            format!("The value {:?}", value)
+        };
+        syn::Error::new(
+            mismatch_span(resolved, pattern),
+            format!(
+                "{} doesn't match the pattern {:?}: {}",
+                value,
+                pattern.value(),
+                reason
+            ),
+        )
+    };
+
+    let mut parts = Vec::new();
+    let mut rest = value;
+    for (i, piece) in pieces.iter().enumerate() {
+        match piece {
+            Piece::Text(text) => match rest.strip_prefix(text.as_str()) {
+                Some(after) => rest = after,
+                None => return Err(mismatch(format!("expected {:?}", text))),
+            },
+            Piece::Field(field) => {
+                let len = match pieces.get(i + 1) {
+                    Some(Piece::Text(text)) => rest
+                        .find(text.as_str())
+                        .ok_or_else(|| mismatch(format!("expected {:?} after {}", text, field)))?,
+                    _ => rest.len(),
+                };
+                if len == 0 {
+                    return Err(mismatch(format!("{} is empty", field)));
+                }
+                parts.push(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    if !rest.is_empty() {
+        return Err(mismatch(if resolved.options.secret {
+            "there's more after the end of the pattern".to_string()
+        } else {
+            format!("unexpected {:?} at the end", rest)
+        }));
+    }
+    Ok(parts)
+}
+
+/// Returns the span to report a mismatch at: the default if it was used, otherwise the pattern
+fn mismatch_span(resolved: &Resolved, pattern: &LitStr) -> Span {
+    if resolved.source == Source::Default {
+        resolved.data.span
+    } else {
+        pattern.span()
+    }
+}
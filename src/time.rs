@@ -0,0 +1,205 @@
+//! Formatting timestamps for `build_time_env!`, and parsing them for `validate = "rfc3339"` and
+//! the `epoch` option
+//!
+//! Only UTC is supported, which avoids needing the time zone database.
+
+use syn::LitStr;
+
+/// The format used when no `format` option is given
+const RFC3339: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// Checks that the `format` option is one `format` accepts
+pub(crate) fn check_format(format: &LitStr) -> syn::Result<()> {
+    self::format(0, Some(format)).map(|_| ())
+}
+
+/// Formats `secs` since the Unix epoch according to the `format` option
+///
+/// This is `"rfc3339"`, `"unix"`, or a pattern where `%Y`, `%m`, `%d`, `%H`, `%M` and `%S` are
+/// replaced by the UTC date and time, `%s` by the seconds since the epoch and `%%` by `%`.
+pub(crate) fn format(secs: i64, format: Option<&LitStr>) -> syn::Result<String> {
+    let pattern = format.map(LitStr::value);
+    let pattern = match pattern.as_deref() {
+        None | Some("rfc3339") => RFC3339,
+        Some("unix") => "%s",
+        Some(pattern) => pattern,
+    };
+
+    let days = secs.div_euclid(86400);
+    let time = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (time / 3600, time / 60 % 60, time % 60);
+
+    let mut out = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => out.push_str(&format!("{:04}", year)),
+            Some('m') => out.push_str(&format!("{:02}", month)),
+            Some('d') => out.push_str(&format!("{:02}", day)),
+            Some('H') => out.push_str(&format!("{:02}", hour)),
+            Some('M') => out.push_str(&format!("{:02}", minute)),
+            Some('S') => out.push_str(&format!("{:02}", second)),
+            Some('s') => out.push_str(&secs.to_string()),
+            Some('%') => out.push('%'),
+            other => {
+                let spec = other.map_or("%".to_string(), |c| format!("%{}", c));
+                return Err(syn::Error::new(
+                    format.map_or_else(proc_macro2::Span::call_site, LitStr::span),
+                    format!(
+                        "Unknown time format specifier {}, expected one of %Y, %m, %d, %H, %M, \
+                         %S, %s or %%",
+                        spec
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) in the proleptic Gregorian calendar
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) in the proleptic Gregorian calendar to days since 1970-01-01
+///
+/// This is Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let day_of_year = (153 * mp + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Parses an RFC 3339 timestamp, such as `2030-01-01T00:00:00Z`, returning the seconds since the
+/// Unix epoch, or a description of why it isn't one
+///
+/// Fractions of a second are allowed and ignored, and a leap second is counted as the next second.
+/// If `secret` is set, the description doesn't quote any part of `s`.
+pub(crate) fn parse_rfc3339(s: &str, secret: bool) -> Result<i64, String> {
+    let bytes = s.as_bytes();
+    let redact = |shown: String, hidden: &str| if secret { hidden.to_string() } else { shown };
+    let number = |range: std::ops::Range<usize>, what: &str| -> Result<i64, String> {
+        bytes
+            .get(range.clone())
+            .filter(|digits| digits.iter().all(u8::is_ascii_digit))
+            .map(|_| s[range].parse().unwrap())
+            .ok_or_else(|| format!("expected the {} as digits", what))
+    };
+    let separator = |i: usize, expected: &[u8], what: &str| -> Result<(), String> {
+        match bytes.get(i) {
+            Some(b) if expected.contains(b) => Ok(()),
+            _ => Err(format!(
+                "expected {} after the {}",
+                expected[0] as char, what
+            )),
+        }
+    };
+
+    let year = number(0..4, "year")?;
+    separator(4, b"-", "year")?;
+    let month = number(5..7, "month")?;
+    separator(7, b"-", "month")?;
+    let day = number(8..10, "day")?;
+    separator(10, b"Tt ", "date")?;
+    let hour = number(11..13, "hour")?;
+    separator(13, b":", "hour")?;
+    let minute = number(14..16, "minute")?;
+    separator(16, b":", "minute")?;
+    let second = number(17..19, "second")?;
+
+    let mut rest = &s[19..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return Err("expected digits after the decimal point".to_string());
+        }
+        rest = &fraction[digits..];
+    }
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2]
+            if [h1, h2, m1, m2].iter().all(|b| b.is_ascii_digit()) =>
+        {
+            let hours = i64::from((h1 - b'0') * 10 + (h2 - b'0'));
+            let minutes = i64::from((m1 - b'0') * 10 + (m2 - b'0'));
+            if hours > 23 || minutes > 59 {
+                return Err(redact(
+                    format!("the offset {} is out of range", rest),
+                    "the offset is out of range",
+                ));
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        [] => return Err("expected a time zone offset, such as Z or +01:00".to_string()),
+        _ => {
+            return Err(redact(
+                format!(
+                    "expected a time zone offset, such as Z or +01:00, not {}",
+                    rest
+                ),
+                "expected a time zone offset, such as Z or +01:00",
+            ))
+        }
+    };
+
+    let leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap_year => 29,
+        2 => 28,
+        _ => {
+            return Err(redact(
+                format!("the month {:02} is out of range", month),
+                "the month is out of range",
+            ))
+        }
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return Err(redact(
+            format!(
+                "the day {:02} is out of range for {:04}-{:02}",
+                day, year, month
+            ),
+            "the day is out of range for its month",
+        ));
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return Err(redact(
+            format!(
+                "the time {:02}:{:02}:{:02} is out of range",
+                hour, minute, second
+            ),
+            "the time is out of range",
+        ));
+    }
+    let days = days_from_civil(year, month, day);
+    Ok(days * 86400 + hour * 3600 + minute * 60 + second - offset)
+}
@@ -0,0 +1,470 @@
+//! Generating a constant struct from a TOML file, for `toml_const!` with the `toml` feature
+//!
+//! Each field is filled from the key with the same name, or the dotted path in its
+//! `#[key = "..."]` attribute, and an `#[env = "VAR"]` attribute lets a build time environment
+//! variable override it. Without a struct definition, one is inferred from the top-level keys.
+
+#[cfg(feature = "toml")]
+use proc_macro2::Literal;
+#[cfg(not(feature = "toml"))]
+use proc_macro2::Span;
+use proc_macro2::TokenStream;
+#[cfg(feature = "toml")]
+use quote::{format_ident, quote};
+#[cfg(feature = "toml")]
+use syn::parse::{Parse, ParseStream};
+#[cfg(feature = "toml")]
+use syn::{Attribute, Ident, LitStr, Token, Type, Visibility};
+
+#[cfg(feature = "toml")]
+use crate::args::Options;
+#[cfg(feature = "toml")]
+use crate::{manifest, reproducible, track, Kind, Source};
+
+/// The input to `toml_const!`: a constant declaration naming the file, and optionally the
+/// struct's definition
+#[cfg(feature = "toml")]
+struct Input {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    ty: Ident,
+    file: LitStr,
+    definition: Option<syn::DeriveInput>,
+}
+
+#[cfg(feature = "toml")]
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![const]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let file = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let definition = if input.is_empty() {
+            None
+        } else {
+            Some(input.parse()?)
+        };
+        Ok(Input {
+            attrs,
+            vis,
+            name,
+            ty,
+            file,
+            definition,
+        })
+    }
+}
+
+/// The kinds of value a field can hold
+#[cfg(feature = "toml")]
+enum Scalar {
+    Str,
+    Bool,
+    Char,
+    /// An integer type, with its smallest and largest values
+    Int(i128, i128),
+    /// A float type, with its name
+    Float(Ident),
+}
+
+/// A field of the struct, and where its value comes from
+#[cfg(feature = "toml")]
+struct Field {
+    ident: Ident,
+    scalar: Scalar,
+    /// The field is an `Option`, which is `None` if the key is missing
+    optional: bool,
+    /// The dotted path of the key in the file
+    key: String,
+    /// The environment variable overriding the value, if any
+    env: Option<LitStr>,
+}
+
+/// Expands `toml_const!`, returning the struct definition and the constant
+#[cfg(feature = "toml")]
+pub(crate) fn expand(input: proc_macro::TokenStream) -> syn::Result<TokenStream> {
+    let Input {
+        attrs,
+        vis,
+        name,
+        ty,
+        file,
+        mut definition,
+    } = syn::parse(input)?;
+    let options = Options::from_config()?;
+    if crate::offline() {
+        return Err(syn::Error::new(
+            file.span(),
+            "Offline mode skips every file, so toml_const! has no values to embed",
+        ));
+    }
+    let (contents, path) = match crate::read_file(&file, &options)? {
+        Kind::Data(data, path) => (data, path),
+        Kind::Name(path, span) => {
+            let path = options.redact(path);
+            return Err(syn::Error::new(span, Source::File.not_found(&path)));
+        }
+    };
+    let shown = options.redact(path.clone());
+    // toml's errors quote the line they're on, which could be part of a secret
+    let table = contents.as_str()?.parse::<toml::Table>().map_err(|e| {
+        let reason = if options.secret {
+            "the file isn't valid TOML".to_string()
+        } else {
+            e.to_string()
+        };
+        syn::Error::new(file.span(), format!("{}: {}", shown, reason))
+    })?;
+    manifest::append(&manifest::Record {
+        macro_name: "toml_const",
+        source: Source::File,
+        name: Some(&path),
+        content: &contents.bytes,
+        secret: options.secret,
+        hermetic: reproducible::non_hermetic(Source::File, Some(&path), &options).is_none(),
+        looked_up: &[],
+    });
+
+    let fields = match &mut definition {
+        Some(definition) => declared_fields(definition, &ty)?,
+        None => inferred_fields(&table),
+    };
+    let mut values = Vec::new();
+    for field in &fields {
+        let ident = &field.ident;
+        let value = field_value(field, &table, &shown, &options)?;
+        values.push(quote!(#ident: #value));
+    }
+
+    let definition = match definition {
+        Some(definition) => quote!(#definition),
+        None => {
+            let declarations = fields.iter().map(|field| {
+                let ident = &field.ident;
+                let ty = match &field.scalar {
+                    Scalar::Str => quote!(&'static str),
+                    Scalar::Bool => quote!(bool),
+                    Scalar::Char => quote!(char),
+                    Scalar::Int(..) => quote!(i64),
+                    Scalar::Float(ty) => quote!(#ty),
+                };
+                quote!(#vis #ident: #ty)
+            });
+            quote! {
+                #[derive(Clone, Copy, Debug, PartialEq)]
+                #vis struct #ty {
+                    #(#declarations,)*
+                }
+            }
+        }
+    };
+    Ok(quote! {
+        #definition
+
+        #(#attrs)*
+        #vis const #name: #ty = #ty {
+            #(#values,)*
+        };
+    })
+}
+
+#[cfg(not(feature = "toml"))]
+pub(crate) fn expand(_input: proc_macro::TokenStream) -> syn::Result<TokenStream> {
+    Err(syn::Error::new(
+        Span::call_site(),
+        "toml_const! requires the `toml` feature",
+    ))
+}
+
+/// Returns the fields of the struct `definition`, removing the `key` and `env` attributes which
+/// are only meaningful to this macro
+#[cfg(feature = "toml")]
+fn declared_fields(definition: &mut syn::DeriveInput, ty: &Ident) -> syn::Result<Vec<Field>> {
+    if definition.ident != *ty {
+        return Err(syn::Error::new(
+            definition.ident.span(),
+            format!("The struct must be named {}, the type of the constant", ty),
+        ));
+    }
+    if !definition.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &definition.generics,
+            "The struct can't be generic",
+        ));
+    }
+    let syn::Data::Struct(syn::DataStruct {
+        fields: syn::Fields::Named(named),
+        ..
+    }) = &mut definition.data
+    else {
+        return Err(syn::Error::new(
+            definition.ident.span(),
+            "toml_const! needs a struct with named fields",
+        ));
+    };
+
+    let mut fields = Vec::new();
+    for field in &mut named.named {
+        let ident = field.ident.clone().expect("named fields have names");
+        let (scalar, optional) = field_type(&field.ty).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &field.ty,
+                "Fields must be `&'static str`, `bool`, `char`, an integer or float type, or an \
+                 `Option` of one of these",
+            )
+        })?;
+        let mut key = ident.to_string();
+        let mut env = None;
+        let mut error = None;
+        field.attrs.retain(|attr| {
+            let name = if attr.path().is_ident("key") {
+                "key"
+            } else if attr.path().is_ident("env") {
+                "env"
+            } else {
+                return true;
+            };
+            match string_attribute(attr, name) {
+                Ok(value) if name == "key" => key = value.value(),
+                Ok(value) => env = Some(value),
+                Err(e) => error = Some(e),
+            }
+            false
+        });
+        if let Some(error) = error {
+            return Err(error);
+        }
+        fields.push(Field {
+            ident,
+            scalar,
+            optional,
+            key,
+            env,
+        });
+    }
+    Ok(fields)
+}
+
+/// Returns the string in an attribute written `#[name = "..."]`
+#[cfg(feature = "toml")]
+fn string_attribute(attr: &Attribute, name: &str) -> syn::Result<LitStr> {
+    match &attr.meta.require_name_value()?.value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(value),
+            ..
+        }) => Ok(value.clone()),
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!("Expected a string, as in `#[{} = \"...\"]`", name),
+        )),
+    }
+}
+
+/// Returns the fields inferred from the top-level keys of `table` which hold strings, integers,
+/// floats or booleans, and whose names are identifiers once `-` is replaced by `_`
+#[cfg(feature = "toml")]
+fn inferred_fields(table: &toml::Table) -> Vec<Field> {
+    table
+        .iter()
+        .filter_map(|(key, value)| {
+            let scalar = match value {
+                toml::Value::String(_) => Scalar::Str,
+                toml::Value::Integer(_) => Scalar::Int(i64::MIN.into(), i64::MAX.into()),
+                toml::Value::Float(_) => Scalar::Float(format_ident!("f64")),
+                toml::Value::Boolean(_) => Scalar::Bool,
+                _ => return None,
+            };
+            let ident = syn::parse_str(&key.replace('-', "_")).ok()?;
+            Some(Field {
+                ident,
+                scalar,
+                optional: false,
+                key: key.clone(),
+                env: None,
+            })
+        })
+        .collect()
+}
+
+/// Returns the kind of value held by a field of type `ty`, and whether it's an `Option`
+#[cfg(feature = "toml")]
+fn field_type(ty: &Type) -> Option<(Scalar, bool)> {
+    match ty {
+        Type::Reference(reference) => {
+            let is_static = reference
+                .lifetime
+                .as_ref()
+                .is_some_and(|lifetime| lifetime.ident == "static");
+            let is_str = matches!(&*reference.elem, Type::Path(p) if p.path.is_ident("str"));
+            (is_static && is_str && reference.mutability.is_none()).then_some((Scalar::Str, false))
+        }
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last()?;
+            if segment.ident == "Option" {
+                let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+                    return None;
+                };
+                let Some(syn::GenericArgument::Type(inner)) = arguments.args.first() else {
+                    return None;
+                };
+                return match field_type(inner)? {
+                    (scalar, false) => Some((scalar, true)),
+                    (_, true) => None,
+                };
+            }
+            if path.path.segments.len() != 1 {
+                return None;
+            }
+            let ident = &segment.ident;
+            let scalar = match ident.to_string().as_str() {
+                "bool" => Scalar::Bool,
+                "char" => Scalar::Char,
+                "f32" | "f64" => Scalar::Float(ident.clone()),
+                "i8" => Scalar::Int(i8::MIN.into(), i8::MAX.into()),
+                "i16" => Scalar::Int(i16::MIN.into(), i16::MAX.into()),
+                "i32" => Scalar::Int(i32::MIN.into(), i32::MAX.into()),
+                "i64" | "isize" => Scalar::Int(i64::MIN.into(), i64::MAX.into()),
+                "i128" => Scalar::Int(i128::MIN, i128::MAX),
+                "u8" => Scalar::Int(0, u8::MAX.into()),
+                "u16" => Scalar::Int(0, u16::MAX.into()),
+                "u32" => Scalar::Int(0, u32::MAX.into()),
+                "u64" | "usize" => Scalar::Int(0, u64::MAX.into()),
+                "u128" => Scalar::Int(0, i128::MAX),
+                _ => return None,
+            };
+            Some((scalar, false))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the tokens for the value of `field`, from its environment variable if that's set and
+/// otherwise from `table`, which was read from the file shown as `shown` in errors
+#[cfg(feature = "toml")]
+fn field_value(
+    field: &Field,
+    table: &toml::Table,
+    shown: &str,
+    options: &Options,
+) -> syn::Result<TokenStream> {
+    let from_env = field.env.as_ref().and_then(|env| {
+        let name = options.env_name(&env.value());
+        let value = track::var(&name).ok()?;
+        Some((env, name, value))
+    });
+    if let Some((env, name, value)) = from_env {
+        reproducible::Mode::from_env()?.check(
+            reproducible::non_hermetic(Source::Env, None, options).as_deref(),
+            &name,
+            env.span(),
+        )?;
+        manifest::append(&manifest::Record {
+            macro_name: "toml_const",
+            source: Source::Env,
+            name: Some(&name),
+            content: value.as_bytes(),
+            secret: options.secret,
+            hermetic: false,
+            looked_up: &[],
+        });
+        let value = match field.scalar {
+            Scalar::Str | Scalar::Char => Some(toml::Value::String(value.clone())),
+            Scalar::Bool => value.trim().parse().ok().map(toml::Value::Boolean),
+            Scalar::Int(..) => value.trim().parse().ok().map(toml::Value::Integer),
+            Scalar::Float(_) => value.trim().parse().ok().map(toml::Value::Float),
+        };
+        let literal = value
+            .ok_or_else(|| "can't be parsed as the field's type".to_string())
+            .and_then(|value| literal(&value, &field.scalar, options.secret));
+        return literal
+            .map(|literal| wrap(literal, field.optional))
+            .map_err(|e| syn::Error::new(env.span(), format!("The value of {} {}", name, e)));
+    }
+
+    let mut value = None;
+    let mut table = Some(table);
+    for part in field.key.split('.') {
+        value = table.and_then(|table| table.get(part));
+        table = value.and_then(toml::Value::as_table);
+    }
+    match value {
+        Some(value) => literal(value, &field.scalar, options.secret)
+            .map(|literal| wrap(literal, field.optional))
+            .map_err(|e| {
+                syn::Error::new(
+                    field.ident.span(),
+                    format!("The key {} in {} {}", field.key, shown, e),
+                )
+            }),
+        None if field.optional => Ok(quote!(::core::option::Option::None)),
+        None => Err(syn::Error::new(
+            field.ident.span(),
+            format!(
+                "{} has no key {} for the field {}",
+                shown, field.key, field.ident
+            ),
+        )),
+    }
+}
+
+/// Wraps `literal` in `Some` if the field is an `Option`
+#[cfg(feature = "toml")]
+fn wrap(literal: TokenStream, optional: bool) -> TokenStream {
+    if optional {
+        quote!(::core::option::Option::Some(#literal))
+    } else {
+        literal
+    }
+}
+
+/// Returns the literal for `value`, or a description of why it can't be held by the field
+#[cfg(feature = "toml")]
+fn literal(value: &toml::Value, scalar: &Scalar, secret: bool) -> Result<TokenStream, String> {
+    let expected = |kind: &str| Err(format!("is a TOML {}, not {}", value.type_str(), kind));
+    match (scalar, value) {
+        (Scalar::Str, toml::Value::String(s)) => Ok(quote!(#s)),
+        (Scalar::Str, _) => expected("a string"),
+        (Scalar::Char, toml::Value::String(s)) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(quote!(#c)),
+                _ => Err("isn't a single character".to_string()),
+            }
+        }
+        (Scalar::Char, _) => expected("a single character string"),
+        (Scalar::Bool, toml::Value::Boolean(b)) => Ok(quote!(#b)),
+        (Scalar::Bool, _) => expected("a boolean"),
+        (Scalar::Int(min, max), toml::Value::Integer(n)) => {
+            if (*min..=*max).contains(&i128::from(*n)) {
+                let literal = Literal::i64_unsuffixed(*n);
+                Ok(quote!(#literal))
+            } else if secret {
+                Err(format!("is outside the range {}..={}", min, max))
+            } else {
+                Err(format!("is {}, outside the range {}..={}", n, min, max))
+            }
+        }
+        (Scalar::Int(..), _) => expected("an integer"),
+        (Scalar::Float(ty), toml::Value::Float(f)) => Ok(if f.is_nan() {
+            quote!(#ty::NAN)
+        } else if f.is_infinite() && *f > 0.0 {
+            quote!(#ty::INFINITY)
+        } else if f.is_infinite() {
+            quote!(#ty::NEG_INFINITY)
+        } else {
+            let literal = Literal::f64_unsuffixed(*f);
+            quote!(#literal)
+        }),
+        (Scalar::Float(_), toml::Value::Integer(n)) => {
+            let literal = Literal::f64_unsuffixed(*n as f64);
+            Ok(quote!(#literal))
+        }
+        (Scalar::Float(_), _) => expected("a float"),
+    }
+}
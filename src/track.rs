@@ -0,0 +1,42 @@
+//! Reading environment variables and files so the compiler knows the expansion depends on them,
+//! with the `nightly-tracking` feature
+//!
+//! Without the feature, cargo only expands the macros again when the crate's own sources change,
+//! so an incremental build can keep a value after its file or environment variable has changed.
+//! The feature uses nightly's `proc_macro::tracked::env_var` and `proc_macro::tracked::path`
+//! instead, which record each read in the crate's dependency info.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+/// Reads the build time environment variable `name`
+#[cfg(feature = "nightly-tracking")]
+pub(crate) fn var_os(name: &str) -> Option<OsString> {
+    match proc_macro::tracked::env_var(name) {
+        Ok(value) => Some(value.into()),
+        Err(std::env::VarError::NotUnicode(value)) => Some(value),
+        Err(std::env::VarError::NotPresent) => None,
+    }
+}
+
+#[cfg(not(feature = "nightly-tracking"))]
+pub(crate) fn var_os(name: &str) -> Option<OsString> {
+    std::env::var_os(name)
+}
+
+/// Reads the build time environment variable `name`, which must be Unicode
+pub(crate) fn var(name: &str) -> Result<String, std::env::VarError> {
+    match var_os(name) {
+        Some(value) => value.into_string().map_err(std::env::VarError::NotUnicode),
+        None => Err(std::env::VarError::NotPresent),
+    }
+}
+
+/// Records that the expansion depends on the file at `path`, which exists
+#[cfg(feature = "nightly-tracking")]
+pub(crate) fn path(path: &Path) {
+    proc_macro::tracked::path(path);
+}
+
+#[cfg(not(feature = "nightly-tracking"))]
+pub(crate) fn path(_path: &Path) {}
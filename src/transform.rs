@@ -0,0 +1,419 @@
+//! Transformations of the value of the source which was found, such as the `template` option
+//!
+//! Options which select part of the value, such as `lines`, can find that the part isn't there, in
+//! which case the next source is tried as if this one didn't exist. Others, such as `minify`, can
+//! find that the value can't be transformed, which is an error unless `on_invalid = "fallback"`
+//! is given. These aren't applied to the default, which is embedded exactly as written.
+
+use syn::LitStr;
+
+use crate::args::{Options, Range};
+use crate::json;
+use crate::track;
+use crate::Data;
+
+/// A value after the transformations have been applied
+pub(crate) enum Transformed {
+    Value(Data),
+    /// The value doesn't contain the part the options select, for this reason
+    Missing(String),
+    /// The value can't be transformed, such as text which isn't UTF-8
+    Invalid(syn::Error),
+}
+
+/// Applies every transformation requested in `options` to `data`
+///
+/// The part of the value is selected first, by byte offset, then it's trimmed, then with comments
+/// stripped by characters, lines, key and pattern, and the rest of the transformations apply to
+/// that.
+pub(crate) fn apply(data: Data, options: &Options) -> syn::Result<Transformed> {
+    if !options.transforms() {
+        return Ok(Transformed::Value(data));
+    }
+    let span = data.span;
+    let mut bytes = data.bytes;
+
+    if options.offset.is_some() || options.length.is_some() {
+        let offset = match &options.offset {
+            Some(offset) => offset.base10_parse()?,
+            None => 0,
+        };
+        let length = match &options.length {
+            Some(length) => Some(length.base10_parse::<usize>()?),
+            None => None,
+        };
+        let end = length.map_or(bytes.len(), |length| offset + length);
+        if end > bytes.len() || offset > bytes.len() {
+            return Ok(Transformed::Missing(format!(
+                "is only {} bytes long",
+                bytes.len()
+            )));
+        }
+        bytes = bytes[offset..end].to_vec();
+        if !options.bytes && std::str::from_utf8(&bytes).is_err() {
+            return Err(syn::Error::new(
+                span,
+                "`offset` and `length` must select whole characters, or use a bytes macro",
+            ));
+        }
+    }
+    if options.trim {
+        bytes = bytes.trim_ascii().to_vec();
+    }
+    if !options.transforms_text() {
+        return Ok(Transformed::Value(Data { bytes, span }));
+    }
+
+    let Ok(mut value) = String::from_utf8(bytes) else {
+        return Ok(Transformed::Invalid(syn::Error::new(
+            span,
+            "The value isn't valid UTF-8, so it can't be transformed as text",
+        )));
+    };
+
+    if let Some(marker) = &options.strip_comments {
+        value = strip_comments(&value, &marker.value());
+    }
+    if let Some(range) = &options.chars {
+        let count = value.chars().count();
+        let end = range.end.unwrap_or(count);
+        if range.start > count || end > count {
+            return Ok(Transformed::Missing(format!(
+                "is only {} characters long",
+                count
+            )));
+        }
+        value = value
+            .chars()
+            .skip(range.start)
+            .take(end - range.start)
+            .collect();
+    }
+
+    if options.line.is_some() && options.lines.is_some() {
+        return Err(syn::Error::new(
+            span,
+            "Only one of `line` and `lines` can be used at once",
+        ));
+    }
+    if let Some(line) = &options.line {
+        let line: usize = line.base10_parse()?;
+        if line == 0 {
+            return Err(syn::Error::new(span, "lines count from 1"));
+        }
+        match value.lines().nth(line - 1) {
+            Some(line) => value = line.to_string(),
+            None => return Ok(Transformed::Missing(format!("has no line {}", line))),
+        }
+    }
+    if let Some(range) = &options.lines {
+        if range.start == 0 {
+            return Err(syn::Error::new(span, "lines count from 1"));
+        }
+        match lines(&value, range) {
+            Some(lines) => value = lines,
+            None => return Ok(Transformed::Missing(describe_missing(range))),
+        }
+    }
+
+    if let Some(key) = &options.key {
+        match key_value(&value, &key.value()) {
+            Some(found) => value = found,
+            None => {
+                return Ok(Transformed::Missing(format!(
+                    "has no {}= line",
+                    key.value()
+                )))
+            }
+        }
+    }
+    if let Some(pattern) = &options.extract {
+        match extract(&value, pattern)? {
+            Some(capture) => value = capture,
+            None => {
+                return Ok(Transformed::Missing(format!(
+                    "doesn't match the pattern {}",
+                    pattern.value()
+                )))
+            }
+        }
+    }
+
+    if options.template {
+        match template(&value) {
+            Ok(templated) => value = templated,
+            Err(e) => return Ok(Transformed::Invalid(syn::Error::new(span, e))),
+        }
+    }
+    if let Some(case) = &options.transform {
+        value = match case.value().as_str() {
+            "lowercase" => value.to_lowercase(),
+            "uppercase" => value.to_uppercase(),
+            c => {
+                return Err(syn::Error::new(
+                    case.span(),
+                    format!("Unknown transform {}, expected lowercase or uppercase", c),
+                ))
+            }
+        };
+    }
+    if let Some(format) = &options.minify {
+        value = match format.value().as_str() {
+            "json" => match json::parse(&value) {
+                Ok(json) => json.to_compact(),
+                Err(e) => return Ok(Transformed::Invalid(syn::Error::new(span, e))),
+            },
+            "whitespace" => collapse_whitespace(&value),
+            f => {
+                return Err(syn::Error::new(
+                    format.span(),
+                    format!("Unknown minify format {}, expected json or whitespace", f),
+                ))
+            }
+        };
+    }
+    if let Some(form) = &options.normalize {
+        value = normalize(&value, form)?;
+    }
+    Ok(Transformed::Value(Data::text(&value, span)))
+}
+
+/// Converts `value` to the Unicode normalization form named by `form`
+#[cfg(feature = "normalize")]
+fn normalize(value: &str, form: &LitStr) -> syn::Result<String> {
+    use unicode_normalization::UnicodeNormalization;
+
+    match form.value().as_str() {
+        "nfc" => Ok(value.nfc().collect()),
+        "nfd" => Ok(value.nfd().collect()),
+        "nfkc" => Ok(value.nfkc().collect()),
+        "nfkd" => Ok(value.nfkd().collect()),
+        f => Err(syn::Error::new(
+            form.span(),
+            format!(
+                "Unknown normalization form {}, expected nfc, nfd, nfkc or nfkd",
+                f
+            ),
+        )),
+    }
+}
+
+#[cfg(not(feature = "normalize"))]
+fn normalize(_value: &str, form: &LitStr) -> syn::Result<String> {
+    Err(syn::Error::new(
+        form.span(),
+        "The `normalize` option requires the `normalize` feature",
+    ))
+}
+
+/// Removes blank lines and lines starting with `marker`, ignoring leading whitespace
+fn strip_comments(value: &str, marker: &str) -> String {
+    let mut out: String = value
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.is_empty() && (marker.is_empty() || !line.starts_with(marker))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if value.ends_with('\n') && !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Replaces each run of whitespace in `value` with a single newline if it contains one, or a
+/// single space otherwise, and removes leading and trailing whitespace
+fn collapse_whitespace(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut pending: Option<char> = None;
+    for c in value.trim().chars() {
+        if c.is_whitespace() {
+            if c == '\n' || pending.is_none() {
+                pending = Some(if c == '\n' { '\n' } else { ' ' });
+            }
+        } else {
+            out.extend(pending.take());
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Returns the lines of `value` in `range` joined by `\n`, if they all exist
+fn lines(value: &str, range: &Range) -> Option<String> {
+    let all: Vec<&str> = value.lines().collect();
+    let end = range.end.unwrap_or(all.len() + 1);
+    if range.start > all.len() || end > all.len() + 1 {
+        return None;
+    }
+    Some(all[range.start - 1..end - 1].join("\n"))
+}
+
+/// Returns the value of the first `KEY=value` line in `value` with the key `key`
+///
+/// This understands files like `/etc/os-release` and `.env`: lines can start with `export`,
+/// whitespace around the `=` is ignored, the value can be wrapped in single or double quotes, and
+/// lines starting with `#` are comments. Like dotenvy, `${NAME}` and `$NAME` in values which aren't
+/// single quoted are replaced by the build time environment variable `NAME`, or otherwise by the
+/// value of the key `NAME` earlier in the file, or by nothing, and `\$` is a literal `$`.
+fn key_value(value: &str, key: &str) -> Option<String> {
+    key_values(value)
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v)
+}
+
+/// Returns every `NAME=value` line in a `.env` style file, in order, with quotes removed and
+/// references to other variables replaced
+pub(crate) fn key_values(value: &str) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for line in value.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((k, v)) = line.split_once('=') else {
+            continue;
+        };
+        let k = k.trim();
+        let v = v.trim();
+        let v = if let Some(quoted) = v.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+            quoted.to_string()
+        } else {
+            let v = v
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(v);
+            interpolate(v, &pairs)
+        };
+        pairs.push((k.to_string(), v));
+    }
+    pairs
+}
+
+/// Replaces `${NAME}` and `$NAME` references in a `.env` value, looking them up in the build time
+/// environment and then in the keys `earlier` in the file
+fn interpolate(value: &str, earlier: &[(String, String)]) -> String {
+    let lookup = |name: &str| {
+        track::var(name).ok().or_else(|| {
+            earlier
+                .iter()
+                .rev()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.clone())
+        })
+    };
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(i) = rest.find(['$', '\\']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(after) = rest.strip_prefix("\\$") {
+            out.push('$');
+            rest = after;
+        } else if let Some(braced) = rest.strip_prefix("${") {
+            let Some(end) = braced.find('}') else {
+                out.push_str(rest);
+                return out;
+            };
+            out.push_str(&lookup(&braced[..end]).unwrap_or_default());
+            rest = &braced[end + 1..];
+        } else if rest.starts_with('$') && rest[1..].starts_with(is_name) {
+            let end = rest[1..]
+                .find(|c| !is_name(c))
+                .map_or(rest.len(), |e| e + 1);
+            out.push_str(&lookup(&rest[1..end]).unwrap_or_default());
+            rest = &rest[end..];
+        } else {
+            out.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Returns the first named capture group of the first match of `pattern` in `value`, or the first
+/// group if none are named, or the whole match if there are no groups
+#[cfg(feature = "regex")]
+fn extract(value: &str, pattern: &LitStr) -> syn::Result<Option<String>> {
+    let regex = regex::Regex::new(&pattern.value())
+        .map_err(|e| syn::Error::new(pattern.span(), format!("Invalid pattern: {}", e)))?;
+    let Some(captures) = regex.captures(value) else {
+        return Ok(None);
+    };
+    let group = regex
+        .capture_names()
+        .position(|name| name.is_some())
+        .unwrap_or(if regex.captures_len() > 1 { 1 } else { 0 });
+    Ok(Some(
+        captures
+            .get(group)
+            .map_or("", |capture| capture.as_str())
+            .to_string(),
+    ))
+}
+
+#[cfg(not(feature = "regex"))]
+fn extract(_value: &str, pattern: &LitStr) -> syn::Result<Option<String>> {
+    Err(syn::Error::new(
+        pattern.span(),
+        "The `extract` option requires the `regex` feature",
+    ))
+}
+
+fn describe_missing(range: &Range) -> String {
+    match range.end {
+        Some(end) => format!("doesn't have lines {} to {}", range.start, end - 1),
+        None => format!("has no line {}", range.start),
+    }
+}
+
+/// Substitutes `${VAR}` and `${VAR:-default}` placeholders in `value` from the build time
+/// environment
+///
+/// `$${` is an escaped `${`. Placeholders naming unset variables without a default are an error.
+pub(crate) fn template(value: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut unresolved = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = escaped;
+        } else if let Some(placeholder) = rest.strip_prefix("${") {
+            let end = placeholder
+                .find('}')
+                .ok_or_else(|| "Unterminated `${` placeholder in template".to_string())?;
+            let (name, default) = match placeholder[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&placeholder[..end], None),
+            };
+            match (track::var(name), default) {
+                (Ok(v), _) => out.push_str(&v),
+                (Err(_), Some(default)) => out.push_str(default),
+                (Err(_), None) => unresolved.push(name),
+            }
+            rest = &placeholder[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+
+    if unresolved.is_empty() {
+        Ok(out)
+    } else {
+        Err(format!(
+            "Unresolved template placeholders: {}",
+            unresolved.join(", ")
+        ))
+    }
+}
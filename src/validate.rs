@@ -0,0 +1,211 @@
+//! Checking the format of a value, for the `validate`, `schemes` and `epoch` options
+
+use syn::LitStr;
+
+use crate::args::Options;
+use crate::{time, Data};
+
+/// Checks that `data` is in the format named by the `validate` option, or is an RFC 3339
+/// timestamp with the `epoch` option
+///
+/// `description` names where the value came from, for the error.
+pub(crate) fn check(data: &Data, options: &Options, description: &str) -> syn::Result<()> {
+    let format = match (&options.validate, options.epoch) {
+        (Some(format), _) => format.clone(),
+        (None, true) => LitStr::new("rfc3339", data.span),
+        (None, false) => {
+            if let Some(schemes) = &options.schemes {
+                return Err(syn::Error::new(
+                    schemes.span(),
+                    "`schemes` is only used with `validate = \"url\"`",
+                ));
+            }
+            return Ok(());
+        }
+    };
+    if options.epoch && format.value() != "rfc3339" {
+        return Err(syn::Error::new(
+            format.span(),
+            "`epoch` needs the value to be an RFC 3339 timestamp, so it can only be used with \
+             `validate = \"rfc3339\"`",
+        ));
+    }
+    let value = data.as_str()?;
+    let result = match format.value().as_str() {
+        "rfc3339" => time::parse_rfc3339(value.trim(), options.secret).map(|_| ()),
+        "url" => {
+            let schemes = options.schemes.as_ref().map(LitStr::value);
+            let schemes: Vec<&str> = match &schemes {
+                Some(schemes) => schemes.split(',').map(str::trim).collect(),
+                None => DEFAULT_SCHEMES.to_vec(),
+            };
+            check_url(value.trim(), &schemes, options.secret)
+        }
+        f => {
+            return Err(syn::Error::new(
+                format.span(),
+                format!("Unknown format {}, expected rfc3339 or url", f),
+            ))
+        }
+    };
+    if let (Some(schemes), false) = (&options.schemes, format.value() == "url") {
+        return Err(syn::Error::new(
+            schemes.span(),
+            "`schemes` is only used with `validate = \"url\"`",
+        ));
+    }
+    result.map_err(|e| {
+        syn::Error::new(
+            data.span,
+            format!(
+                "{} isn't a valid {}: {}",
+                description,
+                format_name(&format.value()),
+                e
+            ),
+        )
+    })
+}
+
+/// The schemes a URL can have when the `schemes` option isn't given
+const DEFAULT_SCHEMES: &[&str] = &["https", "http"];
+
+/// The name of a format in errors
+fn format_name(format: &str) -> &str {
+    match format {
+        "rfc3339" => "RFC 3339 timestamp",
+        "url" => "URL",
+        f => f,
+    }
+}
+
+/// Checks that `url` is an absolute URL with one of `schemes`, returning a description of the
+/// first problem if it isn't
+///
+/// This follows RFC 3986: a scheme, then for URLs with an authority a host and optional port,
+/// then a path, query and fragment made of characters which don't need escaping or `%`
+/// escapes. Web URLs, such as `https` and `wss`, must have a host. If `secret` is set, the
+/// description doesn't quote any part of `url`.
+fn check_url(url: &str, schemes: &[&str], secret: bool) -> Result<(), String> {
+    let redact = |shown: String, hidden: &str| if secret { hidden.to_string() } else { shown };
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return Err("there's no scheme, such as https://".to_string());
+    };
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid_scheme {
+        return Err(redact(
+            format!("{} isn't a valid scheme", scheme),
+            "the scheme isn't valid",
+        ));
+    }
+    if !schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+        let expected = schemes.join(" or ");
+        return Err(redact(
+            format!("the scheme {} isn't allowed, expected {}", scheme, expected),
+            &format!("the scheme isn't allowed, expected {}", expected),
+        ));
+    }
+
+    let web = matches!(
+        scheme.to_ascii_lowercase().as_str(),
+        "http" | "https" | "ws" | "wss" | "ftp"
+    );
+    let rest = match rest.strip_prefix("//") {
+        Some(rest) => {
+            let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            check_authority(&rest[..end], web, secret)?;
+            &rest[end..]
+        }
+        None if web => {
+            return Err(format!(
+                "a {} URL needs a host, as in {}://host",
+                scheme, scheme
+            ))
+        }
+        None => rest,
+    };
+
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err("% must be followed by two hex digits".to_string());
+                }
+            }
+            c if c.is_ascii_alphanumeric() || "-._~!$&'()*+,;=:@/?#".contains(c) => {}
+            c if c.is_whitespace() => return Err("it contains whitespace".to_string()),
+            c => {
+                return Err(redact(
+                    format!("{:?} must be escaped", c),
+                    "it contains a character which must be escaped",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the authority of a URL, the `user@host:port` after `//`
+fn check_authority(authority: &str, needs_host: bool, secret: bool) -> Result<(), String> {
+    let redact = |shown: String, hidden: &str| if secret { hidden.to_string() } else { shown };
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    let (host, port) = if let Some(ip) = host_port.strip_prefix('[') {
+        let Some(end) = ip.find(']') else {
+            return Err("the IPv6 address isn't closed with ]".to_string());
+        };
+        if !ip[..end]
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.')
+        {
+            return Err(redact(
+                format!("{} isn't a valid IPv6 address", &ip[..end]),
+                "the IPv6 address isn't valid",
+            ));
+        }
+        let port = &ip[end + 1..];
+        if !port.is_empty() && !port.starts_with(':') {
+            return Err("expected a port after the IPv6 address".to_string());
+        }
+        (&host_port[..end + 2], port.strip_prefix(':'))
+    } else {
+        match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+
+    if host.is_empty() && needs_host {
+        return Err("the host is empty".to_string());
+    }
+    if !host.starts_with('[') {
+        if let Some(c) = host
+            .chars()
+            .find(|&c| !c.is_ascii_alphanumeric() && !"-._~%!$&'()*+,;=".contains(c))
+        {
+            return Err(redact(
+                format!("the host contains {:?}", c),
+                "the host contains a character which isn't allowed",
+            ));
+        }
+        if host.starts_with('.') || host.contains("..") {
+            return Err(redact(
+                format!("{} isn't a valid host", host),
+                "the host isn't valid",
+            ));
+        }
+    }
+    if let Some(port) = port.filter(|port| !port.is_empty()) {
+        if port.parse::<u16>().is_err() || !port.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(redact(
+                format!("the port {} isn't a number up to 65535", port),
+                "the port isn't a number up to 65535",
+            ));
+        }
+    }
+    Ok(())
+}